@@ -2,10 +2,12 @@
 
 use crate::{check::*, error::*};
 use std::{
+    env,
+    ffi::OsStr,
     fs,
-    os::unix::io::*,
     path::*,
-    process::{Command, Stdio},
+    process::{Command, ExitStatus, Stdio},
+    str::FromStr,
 };
 use walkdir::WalkDir;
 
@@ -16,10 +18,33 @@ pub enum Interface {
     ILP64,
 }
 
-/// CPU list in [TargetList](https://github.com/xianyi/OpenBLAS/blob/v0.3.10/TargetList.txt)
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-#[allow(non_camel_case_types)] // to use original identifiers
-pub enum Target {
+/// Define [Target] with [Target::all] and [Target::as_str] from a single list so that they cannot drift apart
+macro_rules! define_targets {
+    ($($target:ident,)*) => {
+        /// CPU list in [TargetList](https://github.com/xianyi/OpenBLAS/blob/v0.3.10/TargetList.txt)
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        #[allow(non_camel_case_types)] // to use original identifiers
+        pub enum Target {
+            $($target,)*
+        }
+
+        impl Target {
+            /// All targets in TargetList.txt, e.g. to show choices or validate before running `make`
+            pub fn all() -> &'static [Target] {
+                &[$(Target::$target,)*]
+            }
+
+            /// Name in TargetList.txt, e.g. `HASWELL`
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $(Target::$target => stringify!($target),)*
+                }
+            }
+        }
+    };
+}
+
+define_targets! {
     // X86/X86_64 Intel
     P2,
     KATMAI,
@@ -114,9 +139,113 @@ pub enum Target {
     ZARCH_GENERIC,
     Z13,
     Z14,
+
+    // RISC-V 64
+    RISCV64_GENERIC,
+}
+
+impl Target {
+    /// Argument for `make`, e.g. `TARGET=HASWELL`
+    pub fn make_arg(&self) -> String {
+        format!("TARGET={}", self.as_str())
+    }
+
+    /// Generic target for the architecture of Rust target triple, e.g. `ARMV8` for `aarch64-unknown-linux-gnu`
+    ///
+    /// This is useful for cross compiling, where OpenBLAS cannot detect the CPU of the target machine.
+    /// `SSE_GENERIC` is used for `x86_64` since newer kernels like `HASWELL` do not run on older CPUs.
+    ///
+    /// ```
+    /// use openblas_build::*;
+    /// assert_eq!(Target::from_rust_triple("aarch64-unknown-linux-gnu"), Some(Target::ARMV8));
+    /// ```
+    pub fn from_rust_triple(triple: &str) -> Option<Target> {
+        let arch = triple.split('-').next()?;
+        let target = match arch {
+            "x86_64" => Target::SSE_GENERIC,
+            "aarch64" => Target::ARMV8,
+            "armv7" | "armv7a" | "thumbv7neon" => Target::ARMV7,
+            "arm" | "armv6" => Target::ARMV6,
+            "armv5te" => Target::ARMV5,
+            "riscv64" | "riscv64gc" => Target::RISCV64_GENERIC,
+            "powerpc64le" => Target::POWER8,
+            "s390x" => Target::ZARCH_GENERIC,
+            _ => return None,
+        };
+        Some(target)
+    }
+}
+
+impl FromStr for Target {
+    type Err = Error;
+
+    /// Parse target name in TargetList.txt, case-insensitively
+    ///
+    /// ```
+    /// use openblas_build::*;
+    /// let target: Target = "haswell".parse().unwrap();
+    /// assert_eq!(target, Target::HASWELL);
+    /// assert_eq!(target.make_arg(), "TARGET=HASWELL");
+    /// ```
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Target::all()
+            .iter()
+            .find(|target| target.as_str().eq_ignore_ascii_case(s))
+            .copied()
+            .ok_or_else(|| Error::UnsupportedTarget {
+                target: s.to_string(),
+            })
+    }
+}
+
+/// Toolchain for cross compiling, e.g. `aarch64-linux-gnu-gcc` on x86_64 machine
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CrossToolchain {
+    /// C compiler for the target, i.e. `CC`
+    pub cc: String,
+    /// Fortran compiler for the target, i.e. `FC`
+    pub fc: Option<String>,
+    /// C compiler for the build machine to build tools like `getarch`, i.e. `HOSTCC`
+    pub host_cc: String,
+}
+
+/// Threading backend of OpenBLAS
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ThreadingBackend {
+    /// Detected by OpenBLAS, i.e. no argument is passed
+    ///
+    /// Unlike [ThreadingBackend::Pthread], OpenBLAS builds single-threaded if the build machine has only one core.
+    /// This is the default so that `make` is invoked as before this option was added,
+    /// and the deprecated `use_thread` and `use_openmp` of [Configure] take effect only in this case.
+    Auto,
+    /// Single-threaded, i.e. `USE_THREAD=0`
+    Sequential,
+    /// pthreads, i.e. `USE_THREAD=1`
+    Pthread,
+    /// OpenMP, i.e. `USE_OPENMP=1`
+    OpenMP,
 }
 
 /// make option generator
+///
+/// Be sure that some options are mutually exclusive:
+///
+/// - `dynamic_arch` builds kernels for all CPUs in the architecture, and `target` becomes the lowest one
+/// - Both static and shared libraries are built by default,
+///   and setting both `no_static` and `no_shared` is an error [Error::NoLibraryToBuild]
+///
+/// ```
+/// use openblas_build::*;
+/// let cfg = Configure {
+///     dynamic_arch: true,
+///     num_threads: Some(64),
+///     ..Configure::default()
+/// };
+/// assert_eq!(
+///     cfg.make_args(),
+///     vec!["DYNAMIC_ARCH=1", "NUM_THREADS=64"]
+/// );
+/// ```
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Configure {
     pub no_static: bool,
@@ -124,14 +253,57 @@ pub struct Configure {
     pub no_cblas: bool,
     pub no_lapack: bool,
     pub no_lapacke: bool,
+    /// Threading backend, detected by OpenBLAS by default
+    pub threading: ThreadingBackend,
+    #[deprecated(note = "Use `threading` instead")]
     pub use_thread: bool,
+    #[deprecated(note = "Use `threading` instead")]
     pub use_openmp: bool,
     pub dynamic_arch: bool,
     pub interface: Interface,
     pub target: Option<Target>,
+    /// Maximum number of threads, detected from the build machine if `None`
+    pub num_threads: Option<usize>,
+    /// Do not build AVX512 kernels, e.g. when the assembler is too old to support them
+    pub no_avx512: bool,
+    /// Build routines of single precision, e.g. `sgemm`. All precisions are built by default.
+    ///
+    /// If any of precisions is disabled, the enabled ones are passed as `BUILD_SINGLE=1` and so on.
+    pub build_single: bool,
+    /// Build routines of double precision, e.g. `dgemm`
+    pub build_double: bool,
+    /// Build routines of single precision complex, e.g. `cgemm`
+    pub build_complex: bool,
+    /// Build routines of double precision complex, e.g. `zgemm`
+    pub build_complex16: bool,
+    /// Extra arguments passed to `make` verbatim, after generated ones so that they can override
+    pub extra_make_flags: Vec<String>,
+    /// Flags for C compiler, e.g. `-O2 -march=native`, i.e. `COMMON_OPT`
+    ///
+    /// `COMMON_OPT` on the `make` command line replaces the flags computed by OpenBLAS, not appended to them,
+    /// e.g. the default `-O2`, so an optimization level should be included.
+    pub c_flags: Vec<String>,
+    /// Flags for Fortran compiler, i.e. `FCOMMON_OPT`
+    ///
+    /// Same as `c_flags`, this replaces the flags computed by OpenBLAS,
+    /// e.g. `-O2`, `-frecursive` required by thread-safe LAPACK, `-fPIC`, and `-m64`.
+    /// The 64-bit integer flag for ILP64, e.g. `-fdefault-integer-8`, is replaced too,
+    /// and must be included for `interface` of [Interface::ILP64].
+    pub fc_flags: Vec<String>,
+    /// Build and run tests of OpenBLAS, i.e. `make tests`. Only libraries are built by default.
+    pub build_tests: bool,
+    /// Install libraries and headers into this directory by `make install`, i.e. `PREFIX`
+    pub prefix: Option<PathBuf>,
+    /// Toolchain for cross compiling. `target` should be set too since it cannot be detected.
+    pub cross: Option<CrossToolchain>,
+    /// Number of parallel `make` jobs, i.e. `-j`
+    ///
+    /// `NUM_JOBS` set by cargo is used if `None`, and OpenBLAS uses all CPUs if it is not set either.
+    pub jobs: Option<usize>,
 }
 
 impl Default for Configure {
+    #[allow(deprecated)]
     fn default() -> Self {
         Configure {
             no_static: false,
@@ -139,15 +311,135 @@ impl Default for Configure {
             no_cblas: false,
             no_lapack: false,
             no_lapacke: false,
+            threading: ThreadingBackend::Auto,
             use_thread: false,
             use_openmp: false,
             dynamic_arch: false,
             interface: Interface::LP64,
             target: None,
+            num_threads: None,
+            no_avx512: false,
+            build_single: true,
+            build_double: true,
+            build_complex: true,
+            build_complex16: true,
+            extra_make_flags: Vec::new(),
+            c_flags: Vec::new(),
+            fc_flags: Vec::new(),
+            build_tests: false,
+            prefix: None,
+            cross: None,
+            jobs: None,
         }
     }
 }
 
+/// Known failure signatures in `make` log, and hints to fix them
+const MAKE_FAILURE_HINTS: &[(&str, &str)] = &[
+    (
+        "gfortran: command not found",
+        "Install gfortran, or disable LAPACK by `no_lapack`",
+    ),
+    (
+        "No Fortran compiler found",
+        "Install a Fortran compiler e.g. gfortran, or disable LAPACK by `no_lapack`",
+    ),
+    (
+        "perl: not found",
+        "Install perl, which is used in OpenBLAS make system",
+    ),
+    (
+        "perl: command not found",
+        "Install perl, which is used in OpenBLAS make system",
+    ),
+    (
+        "cc: command not found",
+        "Install a C compiler, or specify it by `CC` environment variable",
+    ),
+    (
+        "no such instruction",
+        "The assembler is too old for the target, try `no_avx512` or an older `target`",
+    ),
+];
+
+/// Find known causes of `make` failure from its log, and returns hints to fix them
+///
+/// ```
+/// use openblas_build::*;
+/// let hints = diagnose_make_failure("make[1]: gfortran: command not found");
+/// assert_eq!(hints, vec!["Install gfortran, or disable LAPACK by `no_lapack`"]);
+/// ```
+pub fn diagnose_make_failure(log: &str) -> Vec<String> {
+    let mut hints: Vec<String> = Vec::new();
+    for (signature, hint) in MAKE_FAILURE_HINTS {
+        if log.contains(signature) && !hints.iter().any(|h| h == hint) {
+            hints.push(hint.to_string());
+        }
+    }
+    hints
+}
+
+/// Captured result of `make` command, see [run_make]
+#[derive(Debug, Clone)]
+pub struct MakeResult {
+    pub status: ExitStatus,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl MakeResult {
+    /// Save captured outputs into `out.log` and `err.log` in `dir`
+    pub fn save_logs(&self, dir: impl AsRef<Path>) -> Result<(), Error> {
+        let dir = dir.as_ref();
+        fs::write(dir.join("out.log"), &self.stdout)?;
+        fs::write(dir.join("err.log"), &self.stderr)?;
+        Ok(())
+    }
+}
+
+/// Run `make` in `dir`, and capture its outputs
+///
+/// Error
+/// -----
+/// - `make` cannot start
+/// - `make` returns non-zero status, reported as [Error::MakeFailed] with captured outputs
+pub fn run_make<I, S>(dir: impl AsRef<Path>, args: I) -> Result<MakeResult, Error>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    run_make_with("make", dir, args)
+}
+
+/// Run `make` like [run_make] using specified command, e.g. `gmake`
+///
+/// cargo sets `TARGET` environment variable as target triple (e.g. x86_64-unknown-linux-gnu)
+/// while building build.rs, but `make` of OpenBLAS reads it as CPU target specification.
+/// It is removed from the environment of `make`.
+pub fn run_make_with<I, S>(make: &str, dir: impl AsRef<Path>, args: I) -> Result<MakeResult, Error>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    let out = Command::new(make)
+        .current_dir(dir)
+        .args(args)
+        .env_remove("TARGET")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|error| Error::SubprocessCannotStart { error })?;
+    let result = MakeResult {
+        status: out.status,
+        stdout: out.stdout,
+        stderr: out.stderr,
+    };
+    if result.status.success() {
+        Ok(result)
+    } else {
+        Err(Error::MakeFailed { result })
+    }
+}
+
 /// Deliverables of `make` command
 pub struct Deliverables {
     /// None if `no_static`
@@ -156,10 +448,315 @@ pub struct Deliverables {
     pub shared_lib: Option<LibInspect>,
     /// Inspection what `make` command really show.
     pub make_conf: MakeConf,
+    /// Directory where libraries are installed, i.e. `prefix/lib`. None if `prefix` is not set.
+    pub installed_lib_dir: Option<PathBuf>,
+}
+
+/// Check the directory is writable by creating a temporary file in it
+fn check_writable(dir: &Path) -> Result<(), Error> {
+    let probe = dir.join(".openblas-build-write-test");
+    fs::create_dir_all(dir)
+        .and_then(|_| fs::write(&probe, ""))
+        .and_then(|_| fs::remove_file(&probe))
+        .map_err(|source| Error::PrefixNotWritable {
+            prefix: dir.to_owned(),
+            source,
+        })
+}
+
+/// Environment variables read by [Configure::from_env]
+pub const CONFIGURE_ENV_VARS: &[&str] = &[
+    "OPENBLAS_DYNAMIC_ARCH",
+    "OPENBLAS_NUM_THREADS",
+    "OPENBLAS_USE_OPENMP",
+    "OPENBLAS_TARGET",
+    "OPENBLAS_INTERFACE64",
+];
+
+/// Rust target triple uses musl libc, e.g. `x86_64-unknown-linux-musl` or `armv7-unknown-linux-musleabihf`
+fn is_musl(triple: &str) -> bool {
+    triple
+        .rsplit('-')
+        .next()
+        .is_some_and(|env| env.starts_with("musl"))
+}
+
+/// Boolean value of environment variable, e.g. `1` or `true`. `None` if invalid.
+fn parse_flag(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Summary of build, see [build_and_inspect]
+#[derive(Debug, Clone)]
+pub struct BuildArtifacts {
+    /// Parsed `Makefile.conf`
+    pub make_conf: MakeConf,
+    /// Inspection of the built library, shared library is used if both are built
+    pub inspect: LibInspect,
+    /// Linker flags to link the built library, i.e. the output directory and extra libraries
+    pub link_flags: LinkFlags,
+}
+
+impl BuildArtifacts {
+    /// Combine deliverables of `make` in `out_dir`
+    ///
+    /// Error
+    /// -----
+    /// - Neither static nor shared library exist in the deliverables
+    pub fn from_deliverables(
+        deliv: Deliverables,
+        out_dir: impl AsRef<Path>,
+    ) -> Result<Self, Error> {
+        let out_dir = out_dir.as_ref();
+        let inspect = match deliv.shared_lib.or(deliv.static_lib) {
+            Some(inspect) => inspect,
+            None => return Err(Error::NoLibraryToBuild),
+        };
+        let mut link_flags = deliv
+            .make_conf
+            .c_extra_libs
+            .merge(&deliv.make_conf.f_extra_libs);
+        link_flags.with_search_path(out_dir.to_owned())?;
+        Ok(BuildArtifacts {
+            make_conf: deliv.make_conf,
+            inspect,
+            link_flags,
+        })
+    }
+
+    /// Write detected configuration as JSON for build tools other than cargo
+    ///
+    /// The schema is versioned by `"schema_version"`, and keys are only added in the same version:
+    ///
+    /// ```text
+    /// {
+    ///   "schema_version": 1,
+    ///   "make_conf": { "os_name": "Linux", "core": "HASWELL", "c_extra_libs": { .. }, .. },
+    ///   "link_flags": { "search_paths": [..], "libs": ["gfortran", ..], .. },
+    ///   "capabilities": { "has_cblas": true, "threading": "Pthread", .. }
+    /// }
+    /// ```
+    #[cfg(feature = "serde")]
+    pub fn dump_json<W: std::io::Write>(&self, writer: W) -> Result<(), Error> {
+        #[derive(serde::Serialize)]
+        struct Dump<'a> {
+            schema_version: u32,
+            make_conf: &'a MakeConf,
+            link_flags: &'a LinkFlags,
+            capabilities: Capabilities,
+        }
+        let dump = Dump {
+            schema_version: 1,
+            make_conf: &self.make_conf,
+            link_flags: &self.link_flags,
+            capabilities: Capabilities::from(&self.inspect),
+        };
+        serde_json::to_writer_pretty(writer, &dump).map_err(std::io::Error::from)?;
+        Ok(())
+    }
+}
+
+/// Inspect existing OpenBLAS, e.g. installed in the system, without `make`
+///
+/// `path` is a library file or a directory containing it, see [LibInspect::from_dir].
+/// `Makefile.conf` alongside the library is parsed if exists, and [MakeConf::default] is used otherwise.
+/// This returns the same artifacts as [build_and_inspect] for the library.
+pub fn probe(path: impl AsRef<Path>) -> Result<BuildArtifacts, Error> {
+    let path = path.as_ref();
+    let (lib, dir) = if path.is_dir() {
+        (LibInspect::from_dir(path)?, path)
+    } else {
+        (
+            LibInspect::new(path)?,
+            path.parent().unwrap_or_else(|| Path::new(".")),
+        )
+    };
+    let make_conf_path = dir.join("Makefile.conf");
+    let make_conf = if make_conf_path.exists() {
+        MakeConf::new(make_conf_path)?
+    } else {
+        MakeConf::default()
+    };
+    let (static_lib, shared_lib) = if lib.is_static() {
+        (Some(lib), None)
+    } else {
+        (None, Some(lib))
+    };
+    let deliv = Deliverables {
+        static_lib,
+        shared_lib,
+        make_conf,
+        installed_lib_dir: None,
+    };
+    BuildArtifacts::from_deliverables(deliv, dir)
+}
+
+/// Build OpenBLAS by [Configure::build], and summarize its deliverables
+pub fn build_and_inspect(
+    cfg: Configure,
+    openblas_root: impl AsRef<Path>,
+    out_dir: impl AsRef<Path>,
+) -> Result<BuildArtifacts, Error> {
+    let out_dir = out_dir.as_ref();
+    let deliv = cfg.build(openblas_root, out_dir)?;
+    BuildArtifacts::from_deliverables(deliv, out_dir)
 }
 
 impl Configure {
-    fn make_args(&self) -> Vec<String> {
+    /// Default configure overridden by environment variables listed in [CONFIGURE_ENV_VARS]
+    ///
+    /// - `OPENBLAS_DYNAMIC_ARCH`, `OPENBLAS_USE_OPENMP`, `OPENBLAS_INTERFACE64` accept `1` or `0`
+    /// - `OPENBLAS_USE_OPENMP=0` keeps the default `threading`
+    /// - `OPENBLAS_NUM_THREADS` is a number, and `OPENBLAS_TARGET` is a name in TargetList.txt
+    /// - Unset or empty variables are ignored
+    ///
+    /// Build scripts should print `cargo:rerun-if-env-changed` for each of [CONFIGURE_ENV_VARS].
+    ///
+    /// Error
+    /// -----
+    /// - `OPENBLAS_TARGET` is not a valid target
+    pub fn from_env() -> Result<Self, Error> {
+        Self::from_env_with(|key| env::var(key).ok())
+    }
+
+    /// Same as [Configure::from_env], but variables are looked up by `lookup` instead of the process environment
+    ///
+    /// ```
+    /// use openblas_build::*;
+    /// let cfg = Configure::from_env_with(|key| {
+    ///     (key == "OPENBLAS_DYNAMIC_ARCH").then(|| "1".to_string())
+    /// })
+    /// .unwrap();
+    /// assert!(cfg.dynamic_arch);
+    /// ```
+    pub fn from_env_with<F>(lookup: F) -> Result<Self, Error>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let var = |key: &str| lookup(key).filter(|value| !value.trim().is_empty());
+        let flag = |key: &str| parse_flag(&var(key)?);
+        let mut cfg = Configure::default();
+        if let Some(dynamic_arch) = flag("OPENBLAS_DYNAMIC_ARCH") {
+            cfg.dynamic_arch = dynamic_arch;
+        }
+        if flag("OPENBLAS_USE_OPENMP") == Some(true) {
+            cfg.threading = ThreadingBackend::OpenMP;
+        }
+        if let Some(interface64) = flag("OPENBLAS_INTERFACE64") {
+            cfg.interface = if interface64 {
+                Interface::ILP64
+            } else {
+                Interface::LP64
+            };
+        }
+        if let Some(num_threads) = var("OPENBLAS_NUM_THREADS") {
+            cfg.num_threads = num_threads.trim().parse().ok();
+        }
+        if let Some(target) = var("OPENBLAS_TARGET") {
+            cfg.target = Some(target.trim().parse()?);
+        }
+        Ok(cfg)
+    }
+
+    /// Default configuration for Rust target triple, e.g. `x86_64-unknown-linux-musl`
+    ///
+    /// Only static library is built for musl targets since shared build of OpenBLAS often fails there.
+    /// This can be overridden by fields, and then [Configure::rust_triple_warning] reports it.
+    pub fn for_rust_triple(triple: &str) -> Self {
+        Configure {
+            no_shared: is_musl(triple),
+            ..Configure::default()
+        }
+    }
+
+    /// Warning for options known to be problematic on Rust target triple, e.g. shared library on musl
+    pub fn rust_triple_warning(&self, triple: &str) -> Option<String> {
+        if is_musl(triple) && !self.no_shared {
+            Some(format!(
+                "Shared OpenBLAS library often fails to build on musl target `{}`, set `no_shared` to build static one",
+                triple
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Check options which cannot be set at once
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.no_static && self.no_shared {
+            return Err(Error::NoLibraryToBuild);
+        }
+        if !(self.build_single || self.build_double || self.build_complex || self.build_complex16) {
+            return Err(Error::NoPrecisionToBuild);
+        }
+        Ok(())
+    }
+
+    /// Targets for `make`, `shared` is skipped if `no_shared`, and `tests` is added if `build_tests`
+    ///
+    /// The default target `all` is not used since it builds and runs tests,
+    /// which may fail on some environments even if the libraries are built successfully.
+    pub fn make_targets(&self) -> Vec<&'static str> {
+        let mut targets = vec!["libs", "netlib"];
+        if !self.no_shared {
+            targets.push("shared");
+        }
+        if self.build_tests {
+            targets.push("tests");
+        }
+        targets
+    }
+
+    /// Set the number of parallel `make` jobs, i.e. [Configure::jobs]
+    ///
+    /// ```
+    /// use openblas_build::*;
+    /// let cfg = Configure::default().with_jobs(4);
+    /// assert_eq!(cfg.make_command_args()[0], "-j4");
+    /// ```
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    /// Whole arguments for `make` command, i.e. `-j` flag, [Configure::make_args], and [Configure::make_targets]
+    pub fn make_command_args(&self) -> Vec<String> {
+        let jobs = self
+            .jobs
+            .or_else(|| env::var("NUM_JOBS").ok()?.parse().ok());
+        let mut args = Vec::new();
+        if let Some(jobs) = jobs {
+            args.push(format!("-j{}", jobs));
+        }
+        args.extend(self.make_args());
+        args.extend(self.make_targets().into_iter().map(String::from));
+        args
+    }
+
+    /// Arguments for `make install` run after build, None if `prefix` is not set
+    pub fn make_install_args(&self) -> Option<Vec<String>> {
+        self.prefix.as_ref()?;
+        let mut args = self.make_args();
+        args.push("install".into());
+        Some(args)
+    }
+
+    /// Threading backend in effect, `threading` or the deprecated `use_openmp` and `use_thread` if it is `Auto`
+    #[allow(deprecated)]
+    fn threading_backend(&self) -> ThreadingBackend {
+        match self.threading {
+            ThreadingBackend::Auto if self.use_openmp => ThreadingBackend::OpenMP,
+            ThreadingBackend::Auto if self.use_thread => ThreadingBackend::Pthread,
+            threading => threading,
+        }
+    }
+
+    /// Arguments for `make`, e.g. `NO_SHARED=1`
+    pub fn make_args(&self) -> Vec<String> {
         let mut args = Vec::new();
         if self.no_static {
             args.push("NO_STATIC=1".into())
@@ -176,18 +773,59 @@ impl Configure {
         if self.no_lapacke {
             args.push("NO_LAPACKE=1".into())
         }
-        if self.use_thread {
-            args.push("USE_THREAD=1".into())
+        match self.threading_backend() {
+            ThreadingBackend::Auto => {}
+            ThreadingBackend::Sequential => args.push("USE_THREAD=0".into()),
+            ThreadingBackend::Pthread => args.push("USE_THREAD=1".into()),
+            ThreadingBackend::OpenMP => args.push("USE_OPENMP=1".into()),
         }
-        if self.use_openmp {
-            args.push("USE_OPENMP=1".into())
+        if self.dynamic_arch {
+            args.push("DYNAMIC_ARCH=1".into())
         }
         if matches!(self.interface, Interface::ILP64) {
-            args.push("INTERFACE64=1".into())
+            // OpenBLAS adds the 64-bit integer flag matching the Fortran compiler, e.g. `-fdefault-integer-8`
+            args.push("INTERFACE64=1".into());
+        }
+        if !self.fc_flags.is_empty() {
+            args.push(format!("FCOMMON_OPT={}", self.fc_flags.join(" ")));
+        }
+        if !self.c_flags.is_empty() {
+            args.push(format!("COMMON_OPT={}", self.c_flags.join(" ")));
         }
         if let Some(target) = self.target.as_ref() {
-            args.push(format!("TARGET={:?}", target))
+            args.push(target.make_arg())
+        }
+        if let Some(num_threads) = self.num_threads {
+            args.push(format!("NUM_THREADS={}", num_threads))
+        }
+        if self.no_avx512 {
+            args.push("NO_AVX512=1".into())
         }
+        let precisions = [
+            (self.build_single, "BUILD_SINGLE=1"),
+            (self.build_double, "BUILD_DOUBLE=1"),
+            (self.build_complex, "BUILD_COMPLEX=1"),
+            (self.build_complex16, "BUILD_COMPLEX16=1"),
+        ];
+        // OpenBLAS builds all precisions if none of them is specified
+        if precisions.iter().any(|(enabled, _)| !enabled) {
+            for (enabled, arg) in precisions {
+                if enabled {
+                    args.push(arg.into());
+                }
+            }
+        }
+        if let Some(prefix) = &self.prefix {
+            args.push(format!("PREFIX={}", prefix.display()));
+        }
+        if let Some(cross) = &self.cross {
+            args.push(format!("CC={}", cross.cc));
+            if let Some(fc) = &cross.fc {
+                args.push(format!("FC={}", fc));
+            }
+            args.push(format!("HOSTCC={}", cross.host_cc));
+        }
+        args.extend(self.extra_make_flags.iter().cloned());
         args
     }
 
@@ -223,6 +861,7 @@ impl Configure {
                 None
             },
             make_conf,
+            installed_lib_dir: self.prefix.as_ref().map(|prefix| prefix.join("lib")),
         })
     }
 
@@ -241,6 +880,7 @@ impl Configure {
         openblas_root: impl AsRef<Path>,
         out_dir: impl AsRef<Path>,
     ) -> Result<Deliverables, Error> {
+        self.validate()?;
         let out_dir = out_dir.as_ref();
         if !out_dir.exists() {
             fs::create_dir_all(out_dir)?;
@@ -248,17 +888,18 @@ impl Configure {
 
         // Do not build if libraries and Makefile.conf already exist and are valid
         if let Ok(deliv) = self.inspect(out_dir) {
+            self.install(out_dir)?;
             return Ok(deliv);
         }
 
         // Copy OpenBLAS sources from this crate to `out_dir`
         let root = openblas_root.as_ref();
-        for entry in WalkDir::new(&root) {
+        for entry in WalkDir::new(root) {
             let entry = entry.expect("Unknown IO error while walkdir");
             let dest = out_dir.join(
                 entry
                     .path()
-                    .strip_prefix(&root)
+                    .strip_prefix(root)
                     .expect("Directory entry is not under root"),
             );
             if dest.exists() {
@@ -276,43 +917,651 @@ impl Configure {
 
         // Run `make` as an subprocess
         //
-        // - This will automatically run in parallel without `-j` flag
+        // - This will automatically run in parallel without `-j` flag, see [Configure::jobs]
         // - The `make` of OpenBLAS outputs 30k lines,
-        //   which will be redirected into `out.log` and `err.log`.
-        // - cargo sets `TARGET` environment variable as target triple (e.g. x86_64-unknown-linux-gnu)
-        //   while binding build.rs, but `make` read it as CPU target specification.
+        //   which will be saved into `out.log` and `err.log`.
         //
-        let out = fs::File::create(out_dir.join("out.log")).expect("Cannot create log file");
-        let err = fs::File::create(out_dir.join("err.log")).expect("Cannot create log file");
-        match Command::new("make")
-            .current_dir(out_dir)
-            .stdout(unsafe { Stdio::from_raw_fd(out.into_raw_fd()) }) // this works only for unix
-            .stderr(unsafe { Stdio::from_raw_fd(err.into_raw_fd()) })
-            .args(&self.make_args())
-            .args(&["libs", "netlib", "shared"])
-            .env_remove("TARGET")
-            .check_call()
-        {
-            Ok(_) => {}
-            Err(err @ Error::NonZeroExitStatus { .. }) => {
-                eprintln!(
-                    "{}",
-                    fs::read_to_string(out_dir.join("err.log")).expect("Cannot read log file")
-                );
-                return Err(err);
-            }
-            Err(e) => {
-                return Err(e);
+        let result = match run_make(out_dir, self.make_command_args()) {
+            Ok(result) => result,
+            Err(Error::MakeFailed { result }) => {
+                result.save_logs(out_dir)?;
+                let log = String::from_utf8_lossy(&result.stderr);
+                eprintln!("{}", log);
+                for hint in diagnose_make_failure(&log) {
+                    eprintln!("hint: {}", hint);
+                }
+                return Err(Error::MakeFailed { result });
             }
-        }
+            Err(e) => return Err(e),
+        };
+        result.save_logs(out_dir)?;
 
+        self.install(out_dir)?;
         self.inspect(out_dir)
     }
+
+    /// Run `make install` if `prefix` is set and libraries are not installed yet
+    fn install(&self, out_dir: &Path) -> Result<(), Error> {
+        let (prefix, args) = match (&self.prefix, self.make_install_args()) {
+            (Some(prefix), Some(args)) => (prefix, args),
+            _ => return Ok(()),
+        };
+        if LibInspect::from_dir(prefix.join("lib")).is_ok() {
+            return Ok(());
+        }
+        check_writable(prefix)?;
+        run_make(out_dir, args)?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn target_from_str() {
+        for target in &[
+            Target::HASWELL,
+            Target::SKYLAKEX,
+            Target::SANDYBRIDGE,
+            Target::ZEN,
+            Target::ATOM,
+            Target::ARMV7,
+            Target::ARMV8,
+            Target::CORTEXA53,
+            Target::CORTEXA72,
+            Target::NEOVERSEN1,
+            Target::POWER9,
+            Target::Z14,
+            Target::RISCV64_GENERIC,
+        ] {
+            let name = format!("{:?}", target);
+            assert_eq!(&name.parse::<Target>().unwrap(), target);
+            assert_eq!(&name.to_lowercase().parse::<Target>().unwrap(), target);
+            assert_eq!(target.make_arg(), format!("TARGET={}", name));
+        }
+        assert!("NOT_A_CPU".parse::<Target>().is_err());
+    }
+
+    #[test]
+    fn target_all_roundtrip() {
+        assert_eq!(Target::all().len(), 74);
+        for target in Target::all() {
+            assert_eq!(target.as_str().parse::<Target>().unwrap(), *target);
+            assert_eq!(
+                target.as_str().to_lowercase().parse::<Target>().unwrap(),
+                *target
+            );
+            assert_eq!(target.as_str(), format!("{:?}", target));
+        }
+        assert!(matches!(
+            "HASWEL".parse::<Target>(),
+            Err(Error::UnsupportedTarget { .. })
+        ));
+    }
+
+    #[test]
+    fn target_from_rust_triple() {
+        for (triple, target) in [
+            ("x86_64-unknown-linux-gnu", Target::SSE_GENERIC),
+            ("x86_64-apple-darwin", Target::SSE_GENERIC),
+            ("aarch64-unknown-linux-gnu", Target::ARMV8),
+            ("aarch64-apple-darwin", Target::ARMV8),
+            ("armv7-unknown-linux-gnueabihf", Target::ARMV7),
+            ("arm-unknown-linux-gnueabi", Target::ARMV6),
+            ("riscv64gc-unknown-linux-gnu", Target::RISCV64_GENERIC),
+            ("powerpc64le-unknown-linux-gnu", Target::POWER8),
+            ("s390x-unknown-linux-gnu", Target::ZARCH_GENERIC),
+        ] {
+            assert_eq!(Target::from_rust_triple(triple), Some(target), "{}", triple);
+        }
+        assert_eq!(Target::from_rust_triple("wasm32-unknown-unknown"), None);
+        assert_eq!(Target::from_rust_triple(""), None);
+    }
+
+    #[test]
+    fn make_args() {
+        assert_eq!(Configure::default().make_args(), Vec::<String>::new());
+
+        let cfg = Configure {
+            no_shared: true,
+            no_lapack: true,
+            no_cblas: true,
+            threading: ThreadingBackend::OpenMP,
+            interface: Interface::ILP64,
+            ..Configure::default()
+        };
+        assert_eq!(
+            cfg.make_args(),
+            vec![
+                "NO_SHARED=1",
+                "NO_CBLAS=1",
+                "NO_LAPACK=1",
+                "USE_OPENMP=1",
+                "INTERFACE64=1",
+            ]
+        );
+
+        let cfg = Configure {
+            dynamic_arch: true,
+            target: Some(Target::HASWELL),
+            num_threads: Some(8),
+            ..Configure::default()
+        };
+        assert_eq!(
+            cfg.make_args(),
+            vec!["DYNAMIC_ARCH=1", "TARGET=HASWELL", "NUM_THREADS=8"]
+        );
+
+        let cfg = Configure {
+            target: Some(Target::SKYLAKEX),
+            no_avx512: true,
+            extra_make_flags: vec!["TARGET=HASWELL".into(), "COMMON_OPT=-O2".into()],
+            ..Configure::default()
+        };
+        assert_eq!(
+            cfg.make_args(),
+            vec![
+                "TARGET=SKYLAKEX",
+                "NO_AVX512=1",
+                "TARGET=HASWELL",
+                "COMMON_OPT=-O2"
+            ]
+        );
+    }
+
+    #[test]
+    fn make_failure_hints() {
+        let log = r#"
+make[1]: Entering directory '/tmp/OpenBLAS/lapack-netlib'
+/bin/sh: 1: gfortran: command not found
+make[1]: *** [Makefile:25: lapacklib] Error 127
+"#;
+        assert_eq!(
+            diagnose_make_failure(log),
+            vec!["Install gfortran, or disable LAPACK by `no_lapack`"]
+        );
+
+        let log = r#"
+/bin/sh: 1: perl: not found
+/bin/sh: 1: perl: not found
+Makefile:146: *** No Fortran compiler found. Stop.
+"#;
+        assert_eq!(
+            diagnose_make_failure(log),
+            vec![
+                "Install a Fortran compiler e.g. gfortran, or disable LAPACK by `no_lapack`",
+                "Install perl, which is used in OpenBLAS make system",
+            ]
+        );
+
+        let log = "../kernel/x86_64/sgemm_kernel_16x4_skylakex.c:12: Error: no such instruction: `vpermpd'";
+        assert_eq!(diagnose_make_failure(log).len(), 1);
+
+        assert!(diagnose_make_failure("make: *** [Makefile:42: all] Error 2").is_empty());
+    }
+
+    #[test]
+    fn make_args_threading() {
+        for (threading, arg) in [
+            (ThreadingBackend::Sequential, "USE_THREAD=0"),
+            (ThreadingBackend::Pthread, "USE_THREAD=1"),
+            (ThreadingBackend::OpenMP, "USE_OPENMP=1"),
+        ] {
+            let cfg = Configure {
+                threading,
+                ..Configure::default()
+            };
+            assert_eq!(cfg.make_args(), vec![arg]);
+        }
+        // OpenBLAS detects the backend by default
+        assert!(Configure::default().make_args().is_empty());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn make_args_threading_deprecated() {
+        let cfg = Configure {
+            use_openmp: true,
+            ..Configure::default()
+        };
+        assert_eq!(cfg.make_args(), vec!["USE_OPENMP=1"]);
+        let cfg = Configure {
+            use_thread: true,
+            ..Configure::default()
+        };
+        assert_eq!(cfg.make_args(), vec!["USE_THREAD=1"]);
+        let cfg = Configure {
+            threading: ThreadingBackend::Sequential,
+            use_thread: true,
+            ..Configure::default()
+        };
+        assert_eq!(cfg.make_args(), vec!["USE_THREAD=0"]);
+    }
+
+    #[test]
+    fn make_args_ilp64() {
+        let cfg = Configure {
+            interface: Interface::ILP64,
+            ..Configure::default()
+        };
+        let args = cfg.make_args();
+        assert!(args.contains(&"INTERFACE64=1".to_string()));
+        // `FCOMMON_OPT` on command line would replace the flags computed by OpenBLAS, e.g. `-frecursive`
+        assert!(!args.iter().any(|arg| arg.starts_with("FCOMMON_OPT=")));
+
+        let cfg = Configure {
+            interface: Interface::ILP64,
+            extra_make_flags: vec!["FCOMMON_OPT=-O2 -fdefault-integer-8 -frecursive".into()],
+            ..Configure::default()
+        };
+        assert_eq!(
+            cfg.make_args(),
+            vec![
+                "INTERFACE64=1",
+                "FCOMMON_OPT=-O2 -fdefault-integer-8 -frecursive"
+            ]
+        );
+    }
+
+    #[test]
+    fn make_args_compiler_flags() {
+        // Flags replace the ones computed by OpenBLAS, so they are passed as is
+        let cfg = Configure {
+            c_flags: vec!["-O2".into(), "-march=native".into()],
+            fc_flags: vec!["-O2".into(), "-frecursive".into(), "-fPIC".into()],
+            ..Configure::default()
+        };
+        assert_eq!(
+            cfg.make_args(),
+            vec![
+                "FCOMMON_OPT=-O2 -frecursive -fPIC",
+                "COMMON_OPT=-O2 -march=native",
+            ]
+        );
+
+        // The 64-bit integer flag is not added to `fc_flags`
+        let cfg = Configure {
+            interface: Interface::ILP64,
+            fc_flags: vec![
+                "-O2".into(),
+                "-frecursive".into(),
+                "-fPIC".into(),
+                "-fdefault-integer-8".into(),
+            ],
+            ..cfg
+        };
+        assert_eq!(
+            cfg.make_args(),
+            vec![
+                "INTERFACE64=1",
+                "FCOMMON_OPT=-O2 -frecursive -fPIC -fdefault-integer-8",
+                "COMMON_OPT=-O2 -march=native",
+            ]
+        );
+    }
+
+    #[test]
+    fn make_args_cross() {
+        let cfg = Configure {
+            target: Some(Target::ARMV8),
+            cross: Some(CrossToolchain {
+                cc: "aarch64-linux-gnu-gcc".into(),
+                fc: Some("aarch64-linux-gnu-gfortran".into()),
+                host_cc: "gcc".into(),
+            }),
+            ..Configure::default()
+        };
+        assert_eq!(
+            cfg.make_args(),
+            vec![
+                "TARGET=ARMV8",
+                "CC=aarch64-linux-gnu-gcc",
+                "FC=aarch64-linux-gnu-gfortran",
+                "HOSTCC=gcc"
+            ]
+        );
+
+        let cfg = Configure {
+            no_lapack: true,
+            cross: Some(CrossToolchain {
+                cc: "riscv64-linux-gnu-gcc".into(),
+                fc: None,
+                host_cc: "cc".into(),
+            }),
+            ..Configure::default()
+        };
+        assert_eq!(
+            cfg.make_args(),
+            vec!["NO_LAPACK=1", "CC=riscv64-linux-gnu-gcc", "HOSTCC=cc"]
+        );
+    }
+
+    #[test]
+    fn make_install_args() {
+        assert_eq!(Configure::default().make_install_args(), None);
+
+        let cfg = Configure {
+            no_shared: true,
+            prefix: Some(PathBuf::from("/opt/OpenBLAS")),
+            ..Configure::default()
+        };
+        assert_eq!(cfg.make_args(), vec!["NO_SHARED=1", "PREFIX=/opt/OpenBLAS"]);
+        assert_eq!(
+            cfg.make_install_args().unwrap(),
+            vec!["NO_SHARED=1", "PREFIX=/opt/OpenBLAS", "install"]
+        );
+    }
+
+    #[test]
+    fn prefix_not_writable() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_build/prefix_not_writable");
+        fs::create_dir_all(&root).unwrap();
+        let file = root.join("file");
+        fs::write(&file, "").unwrap();
+        assert!(check_writable(&root.join("prefix")).is_ok());
+        assert!(matches!(
+            check_writable(&file.join("prefix")),
+            Err(Error::PrefixNotWritable { .. })
+        ));
+    }
+
+    #[test]
+    fn make_targets_tests() {
+        assert!(!Configure::default().make_targets().contains(&"tests"));
+        let cfg = Configure {
+            no_shared: true,
+            build_tests: true,
+            ..Configure::default()
+        };
+        assert_eq!(cfg.make_targets(), vec!["libs", "netlib", "tests"]);
+    }
+
+    #[test]
+    fn run_make_capture() {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_build/run_make");
+        fs::create_dir_all(&dir).unwrap();
+        let make = dir.join("fake_make");
+        fs::write(
+            &make,
+            "#!/bin/sh\necho \"make $@ TARGET=$TARGET\"\necho warning >&2\n[ \"$1\" != fail ]\n",
+        )
+        .unwrap();
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&make, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        let make = make.to_str().unwrap();
+
+        let result = run_make_with(make, &dir, ["libs", "netlib"]).unwrap();
+        assert!(result.status.success());
+        assert_eq!(result.stdout, b"make libs netlib TARGET=\n");
+        assert_eq!(result.stderr, b"warning\n");
+        result.save_logs(&dir).unwrap();
+        assert_eq!(fs::read(dir.join("err.log")).unwrap(), b"warning\n");
+
+        match run_make_with(make, &dir, ["fail"]) {
+            Err(Error::MakeFailed { result }) => {
+                assert_eq!(result.status.code(), Some(1));
+                assert_eq!(result.stdout, b"make fail TARGET=\n");
+            }
+            _ => panic!("Non-zero status must be reported"),
+        }
+        assert!(matches!(
+            run_make_with("/not/installed/make", &dir, ["libs"]),
+            Err(Error::SubprocessCannotStart { .. })
+        ));
+    }
+
+    #[test]
+    fn make_command_args() {
+        let cfg = Configure {
+            jobs: Some(1),
+            no_shared: true,
+            ..Configure::default()
+        };
+        assert_eq!(
+            cfg.make_command_args(),
+            vec!["-j1", "NO_SHARED=1", "libs", "netlib"]
+        );
+
+        let cfg = Configure::default().with_jobs(16);
+        assert_eq!(
+            cfg.make_command_args(),
+            vec!["-j16", "libs", "netlib", "shared"]
+        );
+        assert_eq!(
+            Configure::default().with_jobs(1).make_command_args()[0],
+            "-j1"
+        );
+    }
+
+    #[test]
+    fn from_env() {
+        let with_vars = |vars: &[(&str, &str)]| {
+            let vars: HashMap<String, String> = vars
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect();
+            Configure::from_env_with(|key| vars.get(key).cloned())
+        };
+        assert_eq!(with_vars(&[]).unwrap(), Configure::default());
+
+        let cfg = with_vars(&[
+            ("OPENBLAS_DYNAMIC_ARCH", "1"),
+            ("OPENBLAS_NUM_THREADS", "32"),
+            ("OPENBLAS_USE_OPENMP", "true"),
+            ("OPENBLAS_TARGET", "skylakex"),
+            ("OPENBLAS_INTERFACE64", "1"),
+        ])
+        .unwrap();
+        assert!(cfg.dynamic_arch);
+        assert_eq!(cfg.threading, ThreadingBackend::OpenMP);
+        assert_eq!(cfg.num_threads, Some(32));
+        assert_eq!(cfg.target, Some(Target::SKYLAKEX));
+        assert_eq!(cfg.interface, Interface::ILP64);
+
+        // Empty or invalid values are ignored, and `OPENBLAS_USE_OPENMP=0` keeps the default
+        let cfg = with_vars(&[
+            ("OPENBLAS_DYNAMIC_ARCH", ""),
+            ("OPENBLAS_NUM_THREADS", "many"),
+            ("OPENBLAS_USE_OPENMP", "0"),
+            ("OPENBLAS_TARGET", " "),
+            ("OPENBLAS_INTERFACE64", "0"),
+        ])
+        .unwrap();
+        assert_eq!(cfg, Configure::default());
+        assert_eq!(
+            with_vars(&[("OPENBLAS_USE_OPENMP", "maybe")]).unwrap(),
+            Configure::default()
+        );
+
+        assert!(matches!(
+            with_vars(&[("OPENBLAS_TARGET", "NOT_A_CPU")]),
+            Err(Error::UnsupportedTarget { .. })
+        ));
+    }
+
+    #[test]
+    fn musl_static_default() {
+        let cfg = Configure::for_rust_triple("x86_64-unknown-linux-musl");
+        assert!(cfg.no_shared);
+        assert_eq!(cfg.make_args(), vec!["NO_SHARED=1"]);
+        assert_eq!(cfg.rust_triple_warning("x86_64-unknown-linux-musl"), None);
+        assert!(Configure::for_rust_triple("armv7-unknown-linux-musleabihf").no_shared);
+
+        let cfg = Configure::for_rust_triple("x86_64-unknown-linux-gnu");
+        assert_eq!(cfg, Configure::default());
+        assert_eq!(cfg.rust_triple_warning("x86_64-unknown-linux-gnu"), None);
+    }
+
+    #[test]
+    fn musl_shared_override_warning() {
+        let cfg = Configure {
+            no_shared: false,
+            no_static: true,
+            ..Configure::for_rust_triple("aarch64-unknown-linux-musl")
+        };
+        let warning = cfg
+            .rust_triple_warning("aarch64-unknown-linux-musl")
+            .unwrap();
+        assert!(warning.contains("musl"));
+        assert!(warning.contains("aarch64-unknown-linux-musl"));
+    }
+
+    #[test]
+    fn precisions() {
+        let cfg = Configure::default();
+        assert!(!cfg.make_args().iter().any(|arg| arg.starts_with("BUILD_")));
+
+        let cfg = Configure {
+            build_double: false,
+            build_complex16: false,
+            ..Configure::default()
+        };
+        assert!(cfg.validate().is_ok());
+        assert_eq!(cfg.make_args(), vec!["BUILD_SINGLE=1", "BUILD_COMPLEX=1"]);
+
+        let cfg = Configure {
+            build_single: false,
+            build_double: false,
+            build_complex: false,
+            build_complex16: false,
+            ..Configure::default()
+        };
+        assert!(matches!(cfg.validate(), Err(Error::NoPrecisionToBuild)));
+    }
+
+    #[test]
+    fn static_and_shared() {
+        let cfg = Configure::default();
+        assert!(cfg.validate().is_ok());
+        assert_eq!(cfg.make_targets(), vec!["libs", "netlib", "shared"]);
+
+        let cfg = Configure {
+            no_shared: true,
+            ..Configure::default()
+        };
+        assert!(cfg.validate().is_ok());
+        assert_eq!(cfg.make_args(), vec!["NO_SHARED=1"]);
+        assert_eq!(cfg.make_targets(), vec!["libs", "netlib"]);
+
+        let cfg = Configure {
+            no_static: true,
+            ..Configure::default()
+        };
+        assert!(cfg.validate().is_ok());
+        assert_eq!(cfg.make_args(), vec!["NO_STATIC=1"]);
+        assert_eq!(cfg.make_targets(), vec!["libs", "netlib", "shared"]);
+
+        let cfg = Configure {
+            no_static: true,
+            no_shared: true,
+            ..Configure::default()
+        };
+        assert!(matches!(cfg.validate(), Err(Error::NoLibraryToBuild)));
+        assert!(matches!(
+            cfg.build("/not/exist/source", "/not/exist/out"),
+            Err(Error::NoLibraryToBuild)
+        ));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn artifacts_dump_json() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let deliv = Deliverables {
+            static_lib: Some(LibInspect::new(root.join("libtiny.a")).unwrap()),
+            shared_lib: None,
+            make_conf: MakeConf::new(root.join("Makefile.conf")).unwrap(),
+            installed_lib_dir: None,
+        };
+        let artifacts = BuildArtifacts::from_deliverables(deliv, &root).unwrap();
+        let mut buf = Vec::new();
+        artifacts.dump_json(&mut buf).unwrap();
+        let json: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+        assert_eq!(json["schema_version"], 1);
+        assert_eq!(json["make_conf"]["core"], "HASWELL");
+        assert!(json["make_conf"]["f_extra_libs"]["libs"].is_array());
+        assert!(json["link_flags"]["search_paths"].is_array());
+        assert_eq!(json["capabilities"]["has_lapack"], true);
+        assert!(json["capabilities"]["threading"].is_string());
+    }
+
+    #[test]
+    fn probe_system_lib() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let dir = root.join("test_build/probe/lib");
+        fs::create_dir_all(&dir).unwrap();
+        fs::copy(root.join("libtiny.a"), dir.join("libopenblas.a")).unwrap();
+        let _ = fs::remove_file(dir.join("Makefile.conf"));
+
+        let artifacts = probe(&dir).unwrap();
+        assert!(artifacts.inspect.is_static());
+        assert!(artifacts.inspect.has_lapack());
+        assert_eq!(artifacts.make_conf, MakeConf::default());
+        assert_eq!(
+            artifacts.link_flags.search_paths,
+            vec![dir.canonicalize().unwrap()]
+        );
+
+        fs::copy(root.join("Makefile.conf"), dir.join("Makefile.conf")).unwrap();
+        let artifacts = probe(dir.join("libopenblas.a")).unwrap();
+        assert_eq!(artifacts.make_conf.core.as_deref(), Some("HASWELL"));
+        assert!(artifacts.link_flags.libs.contains(&"gfortran".to_string()));
+
+        assert!(matches!(
+            probe(root.join("test_build/probe")),
+            Err(Error::LibraryNotExist { .. })
+        ));
+    }
+
+    #[test]
+    fn artifacts_from_deliverables() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let deliv = Deliverables {
+            static_lib: Some(LibInspect::new(root.join("libtiny.a")).unwrap()),
+            shared_lib: None,
+            make_conf: MakeConf::new(root.join("Makefile.conf")).unwrap(),
+            installed_lib_dir: None,
+        };
+        let artifacts = BuildArtifacts::from_deliverables(deliv, &root).unwrap();
+        assert!(artifacts.inspect.is_static());
+        assert!(artifacts.inspect.has_lapack());
+        assert_eq!(artifacts.make_conf.core.as_deref(), Some("HASWELL"));
+        assert!(artifacts
+            .link_flags
+            .search_paths
+            .contains(&root.canonicalize().unwrap()));
+        assert!(artifacts.link_flags.libs.contains(&"gfortran".to_string()));
+
+        let deliv = Deliverables {
+            static_lib: None,
+            shared_lib: None,
+            make_conf: MakeConf::default(),
+            installed_lib_dir: None,
+        };
+        assert!(matches!(
+            BuildArtifacts::from_deliverables(deliv, &root),
+            Err(Error::NoLibraryToBuild)
+        ));
+    }
+
+    #[ignore]
+    #[test]
+    fn build_and_inspect_default() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let artifacts = build_and_inspect(
+            Configure::default(),
+            root.join("../openblas-src/source"),
+            root.join("test_build/build_and_inspect"),
+        )
+        .unwrap();
+        assert!(!artifacts.inspect.is_static());
+        assert!(artifacts.inspect.has_blas());
+    }
 
     #[ignore]
     #[test]
@@ -331,8 +1580,10 @@ mod tests {
     #[test]
     fn build_no_shared() {
         let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        let mut opt = Configure::default();
-        opt.no_shared = true;
+        let opt = Configure {
+            no_shared: true,
+            ..Configure::default()
+        };
         let detail = opt
             .build(
                 root.join("../openblas-src/source"),
@@ -346,8 +1597,10 @@ mod tests {
     #[test]
     fn build_no_lapacke() {
         let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        let mut opt = Configure::default();
-        opt.no_lapacke = true;
+        let opt = Configure {
+            no_lapacke: true,
+            ..Configure::default()
+        };
         let detail = opt
             .build(
                 root.join("../openblas-src/source"),
@@ -355,6 +1608,7 @@ mod tests {
             )
             .unwrap();
         let shared_lib = detail.shared_lib.unwrap();
+        assert!(shared_lib.has_blas());
         assert!(shared_lib.has_lapack());
         assert!(!shared_lib.has_lapacke());
     }
@@ -363,8 +1617,10 @@ mod tests {
     #[test]
     fn build_openmp() {
         let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
-        let mut opt = Configure::default();
-        opt.use_openmp = true;
+        let opt = Configure {
+            threading: ThreadingBackend::OpenMP,
+            ..Configure::default()
+        };
         let detail = opt
             .build(
                 root.join("../openblas-src/source"),
@@ -373,4 +1629,24 @@ mod tests {
             .unwrap();
         assert!(detail.shared_lib.unwrap().has_lib("gomp"));
     }
+
+    #[ignore]
+    #[test]
+    fn build_sequential() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let opt = Configure {
+            threading: ThreadingBackend::Sequential,
+            ..Configure::default()
+        };
+        let detail = opt
+            .build(
+                root.join("../openblas-src/source"),
+                root.join("test_build/build_sequential"),
+            )
+            .unwrap();
+        assert_eq!(
+            detail.shared_lib.unwrap().threading(),
+            ThreadingModel::Sequential
+        );
+    }
 }
@@ -2,227 +2,4292 @@
 
 use crate::error::*;
 use std::{
-    collections::HashSet,
-    fs,
-    hash::Hash,
-    io::{self, BufRead},
+    collections::{HashMap, HashSet},
+    env,
+    ffi::OsStr,
+    fmt, fs,
+    io::{self, BufRead, Read, Write},
     path::*,
-    process::Command,
+    process::{Child, Command, Output, Stdio},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
 };
 
-/// Parse compiler linker flags, `-L` and `-l`
+/// Parse compiler linker flags, `-L`, `-l`, `-Wl,-rpath`, `-framework` and `-B`
 ///
-/// - Search paths defined by `-L` will be removed if not exists,
-///   and will be canonicalize
+/// - Search paths defined by `-L` and run-time paths defined by `-Wl,-rpath`
+///   will be removed if not exists, and will be canonicalize, except by [LinkFlags::parse_raw]
 ///
 /// ```
 /// use openblas_build::*;
 /// let info = LinkFlags::parse("-L/usr/lib/gcc/x86_64-pc-linux-gnu/10.2.0 -L/usr/lib/gcc/x86_64-pc-linux-gnu/10.2.0/../../../../lib -L/lib/../lib -L/usr/lib/../lib -L/usr/lib/gcc/x86_64-pc-linux-gnu/10.2.0/../../..  -lc").unwrap();
 /// assert_eq!(info.libs, vec!["c"]);
 /// ```
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct LinkFlags {
     /// Existing paths specified by `-L`
     pub search_paths: Vec<PathBuf>,
     /// Libraries specified by `-l`
     pub libs: Vec<String>,
+    /// Libraries specified by exact filename using `-l:`, e.g. `-l:libgfortran.a`
+    pub exact_libs: Vec<String>,
+    /// Existing paths specified by `-Wl,-rpath` or `-Wl,-rpath-link`
+    pub rpaths: Vec<PathBuf>,
+    /// macOS frameworks specified by `-framework`, e.g. `-framework Accelerate`
+    pub frameworks: Vec<String>,
+    /// Existing compiler prefix directories specified by `-B`, which GCC also passes to the linker as `-L`
+    pub b_prefixes: Vec<PathBuf>,
+    /// Existing library files linked directly, e.g. `/usr/lib/libgfortran.a` or `-l/usr/lib/libgfortran.a`
+    pub files: Vec<PathBuf>,
 }
 
-fn as_sorted_vec<T: Hash + Ord>(set: HashSet<T>) -> Vec<T> {
-    let mut v: Vec<_> = set.into_iter().collect();
-    v.sort();
-    v
+/// An entry of [LinkFlags], see [LinkFlags::entries]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LinkEntry {
+    /// `-L/usr/lib`
+    SearchPath(PathBuf),
+    /// `-lgfortran`
+    Lib(String),
+    /// `-l:libgfortran.a`
+    ExactLib(String),
+    /// `-B/usr/lib/gcc`
+    BPrefix(PathBuf),
+    /// `/usr/lib/libgfortran.a`
+    File(PathBuf),
+    /// `-framework Accelerate`
+    Framework(String),
+    /// `-Wl,-rpath,/usr/lib`
+    Rpath(PathBuf),
+}
+
+/// Filename of a library, e.g. `libfoo.a`, `libfoo.so.1` or `libfoo.dylib`
+fn is_lib_file(name: &str) -> bool {
+    name.ends_with(".a")
+        || name.ends_with(".so")
+        || name.contains(".so.")
+        || name.ends_with(".dylib")
+}
+
+/// Push an element if it has not been seen yet, keeping insertion order
+fn push_unique<T: PartialEq>(v: &mut Vec<T>, value: T) {
+    if !v.contains(&value) {
+        v.push(value);
+    }
+}
+
+/// Split flags by whitespace, except in single or double quotes which are removed
+///
+/// Backslashes are kept as is for Windows paths, e.g. `-L"C:\Program Files\lib"` -> `-LC:\Program Files\lib`
+fn split_flags(line: &str) -> Vec<String> {
+    let mut entries = Vec::new();
+    let mut current = String::new();
+    let mut in_entry = false;
+    let mut quote = None;
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_entry = true;
+            }
+            None if c.is_whitespace() => {
+                if in_entry {
+                    entries.push(std::mem::take(&mut current));
+                    in_entry = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_entry = true;
+            }
+        }
+    }
+    if in_entry {
+        entries.push(current);
+    }
+    entries
+}
+
+/// Split flags by whitespace, and expand `@file` by the flags written in the file recursively
+///
+/// Same as GCC, `@file` is kept as is if the file does not exist.
+/// A file already being expanded is skipped to avoid infinite recursion.
+fn expand_response_files(
+    line: &str,
+    resolve: &dyn Fn(&str) -> PathBuf,
+    expanding: &mut Vec<PathBuf>,
+    entries: &mut Vec<String>,
+) -> Result<(), Error> {
+    for entry in split_flags(line) {
+        let file = match entry.strip_prefix('@') {
+            Some(file) => file,
+            None => {
+                entries.push(entry);
+                continue;
+            }
+        };
+        let path = match canonicalize_existing(resolve(file))? {
+            Some(path) => path,
+            None => {
+                entries.push(entry);
+                continue;
+            }
+        };
+        if expanding.contains(&path) {
+            continue;
+        }
+        let content = fs::read_to_string(&path)?;
+        expanding.push(path);
+        expand_response_files(&content, resolve, expanding, entries)?;
+        expanding.pop();
+    }
+    Ok(())
+}
+
+/// Split `/usr/lib-lm` into `/usr/lib` and `-lm` if `/usr/lib-lm` does not exist but `/usr/lib` does
+fn split_jammed_lib<'a>(
+    dir: &'a str,
+    resolve: &dyn Fn(&str) -> PathBuf,
+) -> (&'a str, Option<&'a str>) {
+    if resolve(dir).exists() {
+        return (dir, None);
+    }
+    for (pos, _) in dir.match_indices("-l") {
+        if pos > 0 && resolve(&dir[..pos]).is_dir() {
+            return (&dir[..pos], Some(&dir[pos..]));
+        }
+    }
+    (dir, None)
+}
+
+/// Canonicalize path, or `None` if it does not exist
+fn canonicalize_existing(path: PathBuf) -> Result<Option<PathBuf>, Error> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    match path.canonicalize() {
+        Ok(path) => Ok(Some(path)),
+        Err(_) => Err(Error::CannotCanonicalizePath { path }),
+    }
 }
 
 impl LinkFlags {
+    /// Parse flags, and sort each entries alphabetically
+    ///
+    /// Relative paths are resolved against the current directory.
     pub fn parse(line: &str) -> Result<Self, Error> {
-        let mut search_paths = HashSet::new();
-        let mut libs = HashSet::new();
-        for entry in line.split(" ") {
-            if entry.starts_with("-L") {
-                let path = PathBuf::from(entry.trim_start_matches("-L"));
-                if !path.exists() {
-                    continue;
-                }
-                search_paths.insert(
-                    path.canonicalize()
-                        .map_err(|_| Error::CannotCanonicalizePath { path })?,
-                );
-            }
-            if entry.starts_with("-l") {
-                libs.insert(entry.trim_start_matches("-l").into());
-            }
-        }
-        Ok(LinkFlags {
-            search_paths: as_sorted_vec(search_paths),
-            libs: as_sorted_vec(libs),
-        })
+        Self::parse_relative_to(line, None)
+    }
+
+    /// Parse flags like [LinkFlags::parse], but relative paths are resolved against `base`
+    /// if given, e.g. `-L../lib` in Makefile.conf against the OpenBLAS build directory
+    pub fn parse_relative_to(line: &str, base: Option<&Path>) -> Result<Self, Error> {
+        Self::parse_ordered_relative_to(line, base, false).map(Self::sorted)
+    }
+
+    /// Parse flags like [LinkFlags::parse], but keep paths verbatim
+    ///
+    /// This is purely lexical for sandboxed builds, e.g. Nix or Bazel, where the build system manages paths.
+    /// Paths are neither canonicalized nor removed if not exist,
+    /// `@file` is not expanded, and jammed `-L/usr/lib-lm` is not split.
+    ///
+    /// ```
+    /// use openblas_build::*;
+    /// use std::path::PathBuf;
+    /// let info = LinkFlags::parse_raw("-L/nix/store/virtual/lib -lopenblas").unwrap();
+    /// assert_eq!(info.search_paths, vec![PathBuf::from("/nix/store/virtual/lib")]);
+    /// ```
+    pub fn parse_raw(line: &str) -> Result<Self, Error> {
+        Self::parse_ordered_relative_to(line, None, true).map(Self::sorted)
+    }
+
+    fn sorted(mut flags: Self) -> Self {
+        flags.search_paths.sort();
+        flags.libs.sort();
+        flags.exact_libs.sort();
+        flags.rpaths.sort();
+        flags.frameworks.sort();
+        flags.b_prefixes.sort();
+        flags.files.sort();
+        flags
+    }
+
+    /// Parse flags, and keep each entries in the first-seen order
+    ///
+    /// Use this instead of [LinkFlags::parse] when the link order matters, e.g. for static linking.
+    ///
+    /// ```
+    /// use openblas_build::*;
+    /// let info = LinkFlags::parse_ordered("-lm -lgfortran -lm").unwrap();
+    /// assert_eq!(info.libs, vec!["m", "gfortran"]);
+    /// ```
+    pub fn parse_ordered(line: &str) -> Result<Self, Error> {
+        Self::parse_ordered_relative_to(line, None, false)
+    }
+
+    /// `raw` disables all filesystem accesses for [LinkFlags::parse_raw]
+    fn parse_ordered_relative_to(
+        line: &str,
+        base: Option<&Path>,
+        raw: bool,
+    ) -> Result<Self, Error> {
+        let resolve = |dir: &str| match base {
+            Some(base) => base.join(dir),
+            None => PathBuf::from(dir),
+        };
+        let keep = |path: PathBuf| {
+            if raw {
+                Ok(Some(path))
+            } else {
+                canonicalize_existing(path)
+            }
+        };
+        let mut flags = LinkFlags::default();
+        // `-Wl,-rpath -Wl,/dir` form passes the directory in the next `-Wl,` entry
+        let mut rpath_pending = false;
+        let mut entries = Vec::new();
+        if raw {
+            entries = split_flags(line);
+        } else {
+            expand_response_files(line, &resolve, &mut Vec::new(), &mut entries)?;
+        }
+        let mut i = 0;
+        while i < entries.len() {
+            let entry = entries[i].as_str();
+            i += 1;
+            if entry == "-framework" {
+                // framework name is the next entry
+                if let Some(name) = entries.get(i) {
+                    push_unique(&mut flags.frameworks, name.to_string());
+                    i += 1;
+                }
+                rpath_pending = false;
+                continue;
+            }
+            if let Some(args) = entry.strip_prefix("-Wl,") {
+                for arg in args.split(',') {
+                    if arg == "-rpath" || arg == "-rpath-link" {
+                        rpath_pending = true;
+                        continue;
+                    }
+                    let dir = if let Some(dir) = arg
+                        .strip_prefix("-rpath=")
+                        .or_else(|| arg.strip_prefix("-rpath-link="))
+                    {
+                        dir
+                    } else if rpath_pending && !arg.starts_with('-') {
+                        arg
+                    } else {
+                        rpath_pending = false;
+                        continue;
+                    };
+                    if let Some(path) = keep(resolve(dir))? {
+                        push_unique(&mut flags.rpaths, path);
+                    }
+                }
+                continue;
+            }
+            rpath_pending = false;
+            if let Some(dir) = entry.strip_prefix("-B") {
+                // `-B /dir` form passes the directory in the next entry
+                let dir = if dir.is_empty() {
+                    match entries.get(i) {
+                        Some(next) => {
+                            i += 1;
+                            next.as_str()
+                        }
+                        None => continue,
+                    }
+                } else {
+                    dir
+                };
+                if let Some(path) = keep(resolve(dir))? {
+                    push_unique(&mut flags.b_prefixes, path);
+                }
+                continue;
+            }
+            let mut entry = entry;
+            if let Some(dir) = entry.strip_prefix("-L") {
+                // `-L/usr/lib-lm` jammed without space is split into `-L/usr/lib` and `-lm`
+                let (dir, lib) = if raw {
+                    (dir, None)
+                } else {
+                    split_jammed_lib(dir, &resolve)
+                };
+                if let Some(path) = keep(resolve(dir))? {
+                    push_unique(&mut flags.search_paths, path);
+                }
+                match lib {
+                    Some(lib) => entry = lib,
+                    None => continue,
+                }
+            }
+            // `/usr/lib/libfoo.a` or misused `-l/usr/lib/libfoo.a` links the file directly
+            let file = entry.strip_prefix("-l").unwrap_or(entry);
+            if is_lib_file(file)
+                && !file.starts_with(':')
+                && (file.contains('/') || !entry.starts_with("-l"))
+            {
+                // Missing files are dropped, not linked as a library named by the path
+                if let Some(path) = keep(resolve(file))? {
+                    push_unique(&mut flags.files, path);
+                }
+                continue;
+            }
+            if let Some(file) = entry.strip_prefix("-l:") {
+                push_unique(&mut flags.exact_libs, file.into());
+            } else if entry.starts_with("-l") {
+                push_unique(&mut flags.libs, entry.trim_start_matches("-l").into());
+            }
+        }
+        Ok(flags)
+    }
+
+    /// Union of two flags, e.g. `CEXTRALIB` and `FEXTRALIB`, and sort each entries alphabetically
+    ///
+    /// ```
+    /// use openblas_build::*;
+    /// let c = LinkFlags::parse("-lm -lc").unwrap();
+    /// let f = LinkFlags::parse("-lgfortran -lm").unwrap();
+    /// assert_eq!(c.merge(&f).libs, vec!["c", "gfortran", "m"]);
+    /// ```
+    pub fn merge(&self, other: &LinkFlags) -> LinkFlags {
+        fn union<T: PartialEq + Ord + Clone>(a: &[T], b: &[T]) -> Vec<T> {
+            let mut v = a.to_vec();
+            for value in b {
+                push_unique(&mut v, value.clone());
+            }
+            v.sort();
+            v
+        }
+        LinkFlags {
+            search_paths: union(&self.search_paths, &other.search_paths),
+            libs: union(&self.libs, &other.libs),
+            exact_libs: union(&self.exact_libs, &other.exact_libs),
+            rpaths: union(&self.rpaths, &other.rpaths),
+            frameworks: union(&self.frameworks, &other.frameworks),
+            b_prefixes: union(&self.b_prefixes, &other.b_prefixes),
+            files: union(&self.files, &other.files),
+        }
+    }
+
+    /// Add a search path like `-L`, e.g. the directory of [LibInspect::path]
+    ///
+    /// The path is canonicalized, and ignored if it does not exist or is already added.
+    /// `search_paths` are kept sorted.
+    pub fn with_search_path(&mut self, dir: PathBuf) -> Result<(), Error> {
+        if let Some(dir) = canonicalize_existing(dir)? {
+            if let Err(pos) = self.search_paths.binary_search(&dir) {
+                self.search_paths.insert(pos, dir);
+            }
+        }
+        Ok(())
+    }
+
+    /// Search paths, `-B` prefixes, libraries, exact libraries, files, frameworks, and run-time paths in this order
+    ///
+    /// ```
+    /// use openblas_build::*;
+    /// let info = LinkFlags::parse("-lm -framework Accelerate -l:libgfortran.a").unwrap();
+    /// assert_eq!(
+    ///     info.entries().collect::<Vec<_>>(),
+    ///     vec![
+    ///         LinkEntry::Lib("m".into()),
+    ///         LinkEntry::ExactLib("libgfortran.a".into()),
+    ///         LinkEntry::Framework("Accelerate".into()),
+    ///     ]
+    /// );
+    /// ```
+    pub fn entries(&self) -> impl Iterator<Item = LinkEntry> + '_ {
+        let search_paths = self.search_paths.iter().cloned().map(LinkEntry::SearchPath);
+        let b_prefixes = self.b_prefixes.iter().cloned().map(LinkEntry::BPrefix);
+        let libs = self.libs.iter().cloned().map(LinkEntry::Lib);
+        let exact_libs = self.exact_libs.iter().cloned().map(LinkEntry::ExactLib);
+        let files = self.files.iter().cloned().map(LinkEntry::File);
+        let frameworks = self.frameworks.iter().cloned().map(LinkEntry::Framework);
+        let rpaths = self.rpaths.iter().cloned().map(LinkEntry::Rpath);
+        search_paths
+            .chain(b_prefixes)
+            .chain(libs)
+            .chain(exact_libs)
+            .chain(files)
+            .chain(frameworks)
+            .chain(rpaths)
+    }
+
+    /// Directives which build script should print to link these flags
+    ///
+    /// ```
+    /// use openblas_build::*;
+    /// let info = LinkFlags::parse("-lgfortran -l:libquadmath.a").unwrap();
+    /// assert_eq!(
+    ///     info.to_cargo_directives(),
+    ///     vec![
+    ///         "cargo:rustc-link-lib=gfortran",
+    ///         "cargo:rustc-link-lib=static:+verbatim=libquadmath.a",
+    ///     ]
+    /// );
+    /// ```
+    ///
+    /// - Library names like `Accelerate.framework` are emitted as `framework=Accelerate`
+    /// - Run-time paths are not emitted since `cargo:rustc-link-arg` does not affect dependents
+    pub fn to_cargo_directives(&self) -> Vec<String> {
+        let mut directives = Vec::new();
+        for path in self.search_paths.iter().chain(&self.b_prefixes) {
+            directives.push(format!("cargo:rustc-link-search=native={}", path.display()));
+        }
+        for lib in &self.libs {
+            if let Some(framework) = lib.strip_suffix(".framework") {
+                directives.push(format!("cargo:rustc-link-lib=framework={}", framework));
+            } else {
+                directives.push(format!("cargo:rustc-link-lib={}", lib));
+            }
+        }
+        for lib in &self.exact_libs {
+            let kind = if lib.ends_with(".a") {
+                "static"
+            } else {
+                "dylib"
+            };
+            directives.push(format!("cargo:rustc-link-lib={}:+verbatim={}", kind, lib));
+        }
+        for framework in &self.frameworks {
+            directives.push(format!("cargo:rustc-link-lib=framework={}", framework));
+        }
+        for path in &self.files {
+            let (dir, name) = match (path.parent(), path.file_name()) {
+                (Some(dir), Some(name)) => (dir, name.to_string_lossy()),
+                _ => continue,
+            };
+            let kind = if name.ends_with(".a") {
+                "static"
+            } else {
+                "dylib"
+            };
+            directives.push(format!("cargo:rustc-link-search=native={}", dir.display()));
+            directives.push(format!("cargo:rustc-link-lib={}:+verbatim={}", kind, name));
+        }
+        directives
+    }
+}
+
+/// Quote a flag including whitespace so that [split_flags] reads it back as one entry
+fn quote_flag(flag: String) -> String {
+    if !flag.contains(char::is_whitespace) && !flag.contains(['"', '\'']) {
+        flag
+    } else if flag.contains('"') {
+        format!("'{}'", flag)
+    } else {
+        format!("\"{}\"", flag)
+    }
+}
+
+/// Reconstruct flags, e.g. `-L/usr/lib -lgfortran`
+///
+/// Flags including whitespace are quoted, e.g. `"-L/opt/Open BLAS/lib"`.
+impl fmt::Display for LinkFlags {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut flags = Vec::new();
+        for path in &self.search_paths {
+            flags.push(format!("-L{}", path.display()));
+        }
+        for lib in &self.libs {
+            flags.push(format!("-l{}", lib));
+        }
+        for lib in &self.exact_libs {
+            flags.push(format!("-l:{}", lib));
+        }
+        for path in &self.rpaths {
+            flags.push(format!("-Wl,-rpath,{}", path.display()));
+        }
+        for framework in &self.frameworks {
+            flags.push("-framework".into());
+            flags.push(framework.clone());
+        }
+        for path in &self.b_prefixes {
+            flags.push(format!("-B{}", path.display()));
+        }
+        for path in &self.files {
+            flags.push(path.display().to_string());
+        }
+        let flags: Vec<String> = flags.into_iter().map(quote_flag).collect();
+        write!(f, "{}", flags.join(" "))
     }
 }
 
 /// Parse Makefile.conf which generated by OpenBLAS make system
-#[derive(Debug, Clone, Default)]
+///
+/// Two configurations are equal if their contents are, even if they are parsed from different files.
+#[derive(Debug, Clone, Default, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct MakeConf {
     pub os_name: String,
+    /// Fortran is disabled, i.e. `NOFORTRAN` is set to other than `0`
     pub no_fortran: bool,
+    /// Numeric value of `NOFORTRAN`, e.g. `2` also disables Fortran parts of LAPACK
+    pub no_fortran_level: Option<u8>,
+    /// C compiler selected by OpenBLAS, e.g. `gcc -m64`
+    pub cc: Option<String>,
+    /// Fortran compiler selected by OpenBLAS, e.g. `gfortran`
+    pub fc: Option<String>,
+    /// CPU target specified to `make`, e.g. `SKYLAKEX`
+    pub target: Option<String>,
+    /// CPU core which OpenBLAS actually build for, e.g. `HASWELL`
+    pub core: Option<String>,
+    /// Architecture, e.g. `x86_64`
+    pub arch: Option<String>,
+    /// Built with 64-bit integer interface (ILP64), i.e. `INTERFACE64=1`
+    pub interface64: bool,
+    /// Suffix appended to every exported symbol, i.e. `SYMBOLSUFFIX`, e.g. `64_`
+    pub symbol_suffix: Option<String>,
     pub c_extra_libs: LinkFlags,
     pub f_extra_libs: LinkFlags,
+    /// Path of parsed file, `None` if not parsed from a file
+    #[cfg_attr(feature = "serde", serde(skip))]
+    path: Option<PathBuf>,
+}
+
+impl PartialEq for MakeConf {
+    fn eq(&self, other: &Self) -> bool {
+        // Destructure to compare all fields except `path`
+        let MakeConf {
+            os_name,
+            no_fortran,
+            no_fortran_level,
+            cc,
+            fc,
+            target,
+            core,
+            arch,
+            interface64,
+            symbol_suffix,
+            c_extra_libs,
+            f_extra_libs,
+            path: _,
+        } = self;
+        *os_name == other.os_name
+            && *no_fortran == other.no_fortran
+            && *no_fortran_level == other.no_fortran_level
+            && *cc == other.cc
+            && *fc == other.fc
+            && *target == other.target
+            && *core == other.core
+            && *arch == other.arch
+            && *interface64 == other.interface64
+            && *symbol_suffix == other.symbol_suffix
+            && *c_extra_libs == other.c_extra_libs
+            && *f_extra_libs == other.f_extra_libs
+    }
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    if value.trim().is_empty() {
+        None
+    } else {
+        Some(value.into())
+    }
+}
+
+/// Strip matched surrounding quotes, e.g. `"-lm -lpthread"`
+///
+/// A stray quote of an unterminated value, e.g. `"HASWELL`, is stripped too.
+fn unquote(value: &str) -> &str {
+    let trimmed = value.trim();
+    for quote in ['"', '\''] {
+        if let Some(rest) = trimmed.strip_prefix(quote) {
+            return rest.strip_suffix(quote).unwrap_or(rest);
+        }
+    }
+    value
+}
+
+/// Strip comment starting with `#`, except in single or double quotes
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (pos, c) in line.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => quote = Some(c),
+            None if c == '#' => return &line[..pos],
+            None => {}
+        }
+    }
+    line
+}
+
+/// How a library is linked, see [MakeConf::gfortran_linkage]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Linkage {
+    /// Linked as a static archive, e.g. `-l:libgfortran.a`
+    Static,
+    /// Linked as a shared library, which is required at runtime
+    Dynamic,
 }
 
 impl MakeConf {
     /// Parse from file
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
-        let mut detail = MakeConf::default();
+        let mut detail = MakeConf {
+            path: Some(path.as_ref().to_owned()),
+            ..MakeConf::default()
+        };
         let f = fs::File::open(&path).map_err(|_| Error::MakeConfNotExist {
             out_dir: path.as_ref().to_owned(),
         })?;
         let buf = io::BufReader::new(f);
-        for line in buf.lines() {
-            let line = line.expect("Makefile.conf should not include non-UTF8 string");
-            if line.len() == 0 {
-                continue;
-            }
-            let entry: Vec<_> = line.split("=").collect();
-            if entry.len() != 2 {
-                continue;
+        // Relative paths in linker flags are relative to the directory of Makefile.conf
+        let base = path.as_ref().parent();
+
+        // Collect variables first since `+=` appends to the previous value
+        let mut vars: HashMap<String, String> = HashMap::new();
+        for (line_no, line) in buf.lines().enumerate() {
+            let line = line.map_err(|e| match e.kind() {
+                io::ErrorKind::InvalidData => Error::MakeConfNotUtf8 {
+                    line_no: line_no + 1,
+                },
+                _ => Error::IOError(e),
+            })?;
+            // `lines` removes CRLF, but a stray CR may remain e.g. in MSYS2
+            let line = line.trim_end_matches('\r');
+            let line = strip_comment(line);
+            let (key, value) = match line.split_once('=') {
+                Some(entry) => entry,
+                None => continue,
+            };
+            // Spaces around `=` are allowed, e.g. ` OSNAME = Linux `
+            let (key, value) = (key.trim(), value.trim());
+            if let Some(key) = key.strip_suffix('+') {
+                let current = vars.entry(key.trim_end().into()).or_default();
+                if !current.is_empty() {
+                    current.push(' ');
+                }
+                current.push_str(value);
+            } else {
+                vars.insert(key.into(), value.into());
             }
-            match entry[0] {
-                "OSNAME" => detail.os_name = entry[1].into(),
-                "NOFORTRAN" => detail.no_fortran = true,
-                "CEXTRALIB" => detail.c_extra_libs = LinkFlags::parse(entry[1])?,
-                "FEXTRALIB" => detail.f_extra_libs = LinkFlags::parse(entry[1])?,
+        }
+
+        for (key, value) in &vars {
+            let value = unquote(value);
+            match key.as_str() {
+                "OSNAME" => detail.os_name = value.trim_end().into(),
+                "NOFORTRAN" => {
+                    detail.no_fortran = value.trim() != "0";
+                    detail.no_fortran_level = value.trim().parse().ok();
+                }
+                "CC" => detail.cc = non_empty(value),
+                "FC" => detail.fc = non_empty(value),
+                "TARGET" => detail.target = non_empty(value),
+                "CORE" => detail.core = non_empty(value),
+                "ARCH" => detail.arch = non_empty(value),
+                "INTERFACE64" => detail.interface64 = value.trim() == "1",
+                "SYMBOLSUFFIX" => detail.symbol_suffix = non_empty(value),
+                "CEXTRALIB" => detail.c_extra_libs = LinkFlags::parse_relative_to(value, base)?,
+                "FEXTRALIB" => detail.f_extra_libs = LinkFlags::parse_relative_to(value, base)?,
                 _ => continue,
             }
         }
         Ok(detail)
     }
-}
 
-/// Library inspection using binutils (`nm` and `objdump`) as external command
-///
-/// - Linked shared libraries using `objdump -p` external command.
-/// - Global "T" symbols in the text (code) section of library using `nm -g` external command.
-#[derive(Debug, Clone)]
-pub struct LibInspect {
-    path: PathBuf,
-    pub libs: Vec<String>,
-    pub symbols: Vec<String>,
-}
+    /// Files read to create this, e.g. for `cargo:rerun-if-changed`
+    pub fn deps(&self) -> Vec<PathBuf> {
+        self.path.iter().cloned().collect()
+    }
 
-impl LibInspect {
-    /// Inspect library file
+    /// How libgfortran is linked in `FEXTRALIB`, `None` if it is not referenced
     ///
-    /// Be sure that `nm -g` and `objdump -p` are executed in this function
-    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
-        let path = path.as_ref();
-        if !path.exists() {
-            return Err(Error::LibraryNotExist {
-                path: path.to_owned(),
+    /// `-lgfortran` is static only if the search paths have `libgfortran.a` but no shared one,
+    /// since the linker prefers shared libraries.
+    pub fn gfortran_linkage(&self) -> Option<Linkage> {
+        let flags = &self.f_extra_libs;
+        if let Some(exact) = flags
+            .exact_libs
+            .iter()
+            .find(|lib| lib.starts_with("libgfortran."))
+        {
+            return Some(if exact.ends_with(".a") {
+                Linkage::Static
+            } else {
+                Linkage::Dynamic
             });
         }
-
-        let nm_out = Command::new("nm").arg("-g").arg(path).output()?;
-
-        // assumes `nm` output like following:
-        //
-        // ```
-        // 0000000000909b30 T zupmtr_
-        // ```
-        let mut symbols: Vec<_> = nm_out
-            .stdout
-            .lines()
-            .flat_map(|line| {
-                let line = line.expect("nm output should not include non-UTF8 output");
-                let entry: Vec<_> = line.trim().split(" ").collect();
-                if entry.len() == 3 && entry[1] == "T" {
-                    Some(entry[2].into())
-                } else {
-                    None
-                }
-            })
-            .collect();
-        symbols.sort(); // sort alphabetically
-
-        let mut libs: Vec<_> = Command::new("objdump")
-            .arg("-p")
-            .arg(path)
-            .output()?
-            .stdout
-            .lines()
-            .flat_map(|line| {
-                let line = line.expect("objdump output should not include non-UTF8 output");
-                if line.trim().starts_with("NEEDED") {
-                    Some(line.trim().trim_start_matches("NEEDED").trim().to_string())
-                } else {
-                    None
-                }
-            })
-            .collect();
-        libs.sort();
-
-        Ok(LibInspect {
-            path: path.into(),
-            libs,
-            symbols,
-        })
+        if !flags.libs.iter().any(|lib| lib == "gfortran") {
+            return None;
+        }
+        for dir in &flags.search_paths {
+            let shared = ["so", "dylib", "dll.a"]
+                .iter()
+                .any(|ext| dir.join(format!("libgfortran.{}", ext)).exists());
+            if shared {
+                return Some(Linkage::Dynamic);
+            }
+            if dir.join("libgfortran.a").exists() {
+                return Some(Linkage::Static);
+            }
+        }
+        Some(Linkage::Dynamic)
     }
 
-    pub fn has_cblas(&self) -> bool {
-        for sym in &self.symbols {
-            if sym.starts_with("cblas_") {
-                return true;
-            }
+    /// Write as a minimal Makefile.conf, which can be parsed by [MakeConf::new] again
+    pub fn write<W: Write>(&self, mut w: W) -> Result<(), Error> {
+        writeln!(w, "OSNAME={}", self.os_name)?;
+        if let Some(arch) = &self.arch {
+            writeln!(w, "ARCH={}", arch)?;
+        }
+        if let Some(cc) = &self.cc {
+            writeln!(w, "CC={}", cc)?;
         }
-        return false;
+        if let Some(fc) = &self.fc {
+            writeln!(w, "FC={}", fc)?;
+        }
+        match self.no_fortran_level {
+            Some(level) => writeln!(w, "NOFORTRAN={}", level)?,
+            None if self.no_fortran => writeln!(w, "NOFORTRAN=1")?,
+            None => {}
+        }
+        if self.interface64 {
+            writeln!(w, "INTERFACE64=1")?;
+        }
+        if let Some(suffix) = &self.symbol_suffix {
+            writeln!(w, "SYMBOLSUFFIX={}", suffix)?;
+        }
+        if let Some(target) = &self.target {
+            writeln!(w, "TARGET={}", target)?;
+        }
+        if let Some(core) = &self.core {
+            writeln!(w, "CORE={}", core)?;
+        }
+        writeln!(w, "CEXTRALIB={}", self.c_extra_libs)?;
+        writeln!(w, "FEXTRALIB={}", self.f_extra_libs)?;
+        Ok(())
     }
+}
 
-    pub fn has_lapack(&self) -> bool {
-        for sym in &self.symbols {
-            if sym == "dsyev_" {
-                return true;
-            }
+/// CPU detected by `getarch` of OpenBLAS, see [parse_getarch]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GetArchInfo {
+    /// CPU core, e.g. `HASWELL`
+    pub core: Option<String>,
+    /// Architecture, e.g. `x86_64`
+    pub arch: Option<String>,
+    pub num_cores: Option<usize>,
+    /// L1 data cache size in bytes
+    pub l1_data_size: Option<u64>,
+    /// L2 cache size in bytes
+    pub l2_size: Option<u64>,
+    /// L3 cache size in bytes
+    pub l3_size: Option<u64>,
+}
+
+impl GetArchInfo {
+    /// Detected core is `target`, e.g. to check it is not fallen back to another one in cross compiling
+    pub fn matches_target(&self, target: &crate::Target) -> bool {
+        self.core
+            .as_deref()
+            .is_some_and(|core| core.eq_ignore_ascii_case(target.as_str()))
+    }
+}
+
+/// Parse output of `getarch`, both of `Makefile.conf` form `CORE=HASWELL`
+/// and `config.h` form `#define L2_SIZE 262144`
+///
+/// ```
+/// use openblas_build::*;
+/// let info = parse_getarch("CORE=HASWELL\n#define L2_SIZE 262144\n");
+/// assert_eq!(info.core.as_deref(), Some("HASWELL"));
+/// assert_eq!(info.l2_size, Some(262144));
+/// ```
+pub fn parse_getarch(output: &str) -> GetArchInfo {
+    let mut info = GetArchInfo::default();
+    for line in output.lines() {
+        let line = line.trim();
+        let entry = match line.strip_prefix("#define ") {
+            Some(define) => define.trim().split_once(char::is_whitespace),
+            None => line.split_once('='),
+        };
+        let (key, value) = match entry {
+            Some((key, value)) => (key.trim(), unquote(value.trim())),
+            None => continue,
+        };
+        match key {
+            "CORE" | "CORENAME" => info.core = non_empty(value),
+            "ARCH" => info.arch = non_empty(value),
+            "NUM_CORES" => info.num_cores = value.parse().ok(),
+            "L1_DATA_SIZE" => info.l1_data_size = value.parse().ok(),
+            "L2_SIZE" => info.l2_size = value.parse().ok(),
+            "L3_SIZE" => info.l3_size = value.parse().ok(),
+            _ => continue,
         }
-        return false;
     }
+    info
+}
 
-    pub fn has_lapacke(&self) -> bool {
-        for sym in &self.symbols {
-            if sym.starts_with("LAPACKE_") {
-                return true;
+/// Parse pkg-config file, e.g. `openblas.pc` shipped by distributions, without running `pkg-config`
+///
+/// ```text
+/// prefix=/usr
+/// libdir=${prefix}/lib
+///
+/// Name: openblas
+/// Libs: -L${libdir} -lopenblas
+/// Libs.private: -lgfortran -lm
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PkgConfig {
+    /// Variables defined in the file, with `${...}` already substituted
+    pub variables: HashMap<String, String>,
+    /// Parsed `Libs` field
+    pub libs: LinkFlags,
+    /// Parsed `Libs.private` field, necessary for static linking
+    pub libs_private: LinkFlags,
+}
+
+impl PkgConfig {
+    /// Parse from file
+    pub fn parse<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let content = fs::read_to_string(path)?;
+        let mut pc = PkgConfig::default();
+        for line in content.lines() {
+            // Strip comment
+            let line = match line.find('#') {
+                Some(pos) => &line[..pos],
+                None => line,
+            };
+            // `name=value` defines a variable, and `Keyword: value` defines a field
+            let pos = match line.find([':', '=']) {
+                Some(pos) => pos,
+                None => continue,
+            };
+            let key = line[..pos].trim();
+            let value = pc.substitute(line[pos + 1..].trim())?;
+            if line[pos..].starts_with('=') {
+                pc.variables.insert(key.into(), value);
+                continue;
+            }
+            match key {
+                "Libs" => pc.libs = LinkFlags::parse(&value)?,
+                "Libs.private" => pc.libs_private = LinkFlags::parse(&value)?,
+                _ => continue,
             }
         }
-        return false;
+        Ok(pc)
     }
 
-    pub fn has_lib(&self, name: &str) -> bool {
-        for lib in &self.libs {
-            if let Some(stem) = lib.split(".").next() {
-                if stem == format!("lib{}", name) {
-                    return true;
-                }
+    /// Replace `${name}` by the variables defined so far
+    fn substitute(&self, value: &str) -> Result<String, Error> {
+        let mut out = String::new();
+        let mut rest = value;
+        while let Some(start) = rest.find("${") {
+            out.push_str(&rest[..start]);
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => break,
             };
+            let name = &rest[start + 2..end];
+            let var = self
+                .variables
+                .get(name)
+                .ok_or_else(|| Error::PkgConfigUndefinedVariable { name: name.into() })?;
+            out.push_str(var);
+            rest = &rest[end + 1..];
         }
-        return false;
+        out.push_str(rest);
+        Ok(out)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Library inspection using binutils (`nm` and `objdump`) as external command
+///
+/// - Linked shared libraries using `objdump -p` external command, or `otool -L` on macOS.
+///   This is skipped for static archives since they do not have NEEDED entries.
+/// - Global "T" symbols in the text (code) section of library using `nm -g` external command.
+/// - `readelf` is used instead if `nm` or `objdump` is not installed.
+/// - `dumpbin /SYMBOLS` and `dumpbin /DEPENDENTS` are used on Windows.
+#[derive(Debug, Clone)]
+pub struct LibInspect {
+    path: PathBuf,
+    real_path: PathBuf,
+    is_static: bool,
+    pub libs: Vec<String>,
+    /// Global "T" symbols, sorted alphabetically
+    pub symbols: Vec<String>,
+    /// All global symbols with its type shown by `nm`, e.g. `("dsyev_", 'T')` or `("pthread_create", 'U')`
+    pub typed_symbols: Vec<(String, char)>,
+    /// Size of the library file, `0` if unknown
+    pub size_bytes: u64,
+    runpaths: Vec<PathBuf>,
+    /// `objdump` used after inspection, e.g. by [LibInspect::bitness]
+    objdump: String,
+    /// Timeout of external commands executed after inspection
+    timeout: Duration,
+}
 
-    #[test]
-    fn detail_from_makefile_conf() {
-        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Makefile.conf");
-        assert!(path.exists());
-        let detail = MakeConf::new(path).unwrap();
-        assert!(!detail.no_fortran);
+/// Check the file is Mach-O object, or a BSD-style archive of them, whose symbols have a leading `_`
+fn is_mach_o(path: &Path) -> Result<bool, Error> {
+    let mut header = Vec::new();
+    fs::File::open(path)?.take(24).read_to_end(&mut header)?;
+    let magic = header.get(..4);
+    if matches!(
+        magic,
+        Some([0xfe, 0xed, 0xfa, 0xce | 0xcf])
+            | Some([0xce | 0xcf, 0xfa, 0xed, 0xfe])
+            | Some([0xca, 0xfe, 0xba, 0xbe])
+    ) {
+        return Ok(true);
     }
+    // macOS `ar` puts `__.SYMDEF` first, and stores names by `#1/<length>`
+    Ok(header.starts_with(b"!<arch>\n")
+        && (header[8..].starts_with(b"__.SYMDEF") || header[8..].starts_with(b"#1/")))
+}
 
-    #[test]
-    fn detail_from_nofortran_conf() {
-        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("nofortran.conf");
-        assert!(path.exists());
-        let detail = MakeConf::new(path).unwrap();
-        assert!(detail.no_fortran);
+/// Check the file is a static archive by its extension or `ar` magic
+fn is_archive(path: &Path) -> Result<bool, Error> {
+    if path.extension() == Some("a".as_ref()) {
+        return Ok(true);
+    }
+    let mut magic = [0_u8; 8];
+    let mut f = fs::File::open(path)?;
+    Ok(f.read_exact(&mut magic).is_ok() && &magic == b"!<arch>\n")
+}
+
+/// Parse symbols from `nm -g` output
+///
+/// assumes `nm` output like following:
+///
+/// ```text
+/// 0000000000909b30 T zupmtr_
+/// 0000000000a1c2e0 W openblas_set_num_threads_
+///                  U pthread_create
+/// ```
+///
+/// Undefined symbols (`U`) do not have an address.
+fn parse_nm(stdout: &[u8]) -> Vec<(String, char)> {
+    let mut symbols: Vec<_> = String::from_utf8_lossy(stdout)
+        .lines()
+        .flat_map(|line| {
+            let entry: Vec<_> = line.trim().split(' ').collect();
+            match entry.len() {
+                3 if entry[1].len() == 1 => Some((entry[2].to_string(), entry[1].chars().next()?)),
+                2 if entry[0] == "U" => Some((entry[1].to_string(), 'U')),
+                _ => None,
+            }
+        })
+        .collect();
+    symbols.sort(); // sort alphabetically
+    symbols
+}
+
+/// Parse NEEDED entries from `objdump -p` output
+///
+/// assumes `objdump -p` output like following:
+///
+/// ```text
+///   NEEDED               libgfortran.so.5
+/// ```
+fn parse_objdump_needed(stdout: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .flat_map(|line| {
+            if line.trim().starts_with("NEEDED") {
+                Some(line.trim().trim_start_matches("NEEDED").trim().to_string())
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Parse RPATH and RUNPATH entries from `objdump -p` output
+///
+/// assumes `objdump -p` output like following:
+///
+/// ```text
+///   RUNPATH              /opt/OpenBLAS/lib:$ORIGIN/../lib
+/// ```
+fn parse_objdump_runpaths(stdout: &[u8]) -> Vec<PathBuf> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .flat_map(|line| {
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next()) {
+                (Some("RPATH" | "RUNPATH"), Some(paths)) => {
+                    paths.split(':').map(PathBuf::from).collect()
+                }
+                _ => Vec::new(),
+            }
+        })
+        .collect()
+}
+
+/// Parse RPATH and RUNPATH entries from `readelf -d` output
+///
+/// assumes `readelf -d` output like following:
+///
+/// ```text
+///  0x000000000000001d (RUNPATH)            Library runpath: [/opt/OpenBLAS/lib]
+/// ```
+fn parse_readelf_runpaths(stdout: &[u8]) -> Vec<PathBuf> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .filter(|line| line.contains("(RPATH)") || line.contains("(RUNPATH)"))
+        .flat_map(|line| {
+            let start = line.find('[').map_or(line.len(), |start| start + 1);
+            let end = line.rfind(']').unwrap_or(start).max(start);
+            line[start..end]
+                .split(':')
+                .filter(|path| !path.is_empty())
+                .map(PathBuf::from)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Run-time paths cannot be read from the output, e.g. of `otool -L`
+fn no_runpaths(_stdout: &[u8]) -> Vec<PathBuf> {
+    Vec::new()
+}
+
+/// `readelf` in the same toolchain as `objdump`,
+/// e.g. `aarch64-linux-gnu-readelf` for `aarch64-linux-gnu-objdump`
+fn readelf_for(objdump: &str) -> String {
+    match objdump.strip_suffix("objdump") {
+        Some(prefix) => format!("{}readelf", prefix),
+        None => "readelf".into(),
+    }
+}
+
+/// Parse NEEDED entries from `readelf -d` output
+///
+/// assumes `readelf -d` output like following:
+///
+/// ```text
+///  0x0000000000000001 (NEEDED)             Shared library: [libgfortran.so.5]
+/// ```
+fn parse_readelf_needed(stdout: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .flat_map(|line| {
+            if !line.contains("(NEEDED)") {
+                return None;
+            }
+            let start = line.find('[')?;
+            let end = line.rfind(']')?;
+            Some(line.get(start + 1..end)?.to_string())
+        })
+        .collect()
+}
+
+/// Parse global symbols from `readelf -W --syms` output into `nm` symbol types
+///
+/// assumes `readelf -W --syms` output like following:
+///
+/// ```text
+///    Num:    Value          Size Type    Bind   Vis      Ndx Name
+///     12: 0000000000001139    11 FUNC    GLOBAL DEFAULT   14 dsyev_
+///     13: 0000000000000000     0 FUNC    GLOBAL DEFAULT  UND pthread_create@GLIBC_2.2.5 (2)
+/// ```
+fn parse_readelf_syms(stdout: &[u8]) -> Vec<(String, char)> {
+    let mut symbols: Vec<_> = String::from_utf8_lossy(stdout)
+        .lines()
+        .flat_map(|line| {
+            let entry: Vec<_> = line.split_whitespace().collect();
+            if entry.len() < 8 || !entry[0].ends_with(':') {
+                return None;
+            }
+            let (ty, bind, ndx) = (entry[3], entry[4], entry[6]);
+            let ty = match (ndx, ty, bind) {
+                ("UND", _, _) => 'U',
+                (_, "FUNC", "GLOBAL") => 'T',
+                (_, "FUNC", "WEAK") => 'W',
+                (_, "OBJECT", "GLOBAL") => 'D',
+                (_, "OBJECT", "WEAK") => 'V',
+                _ => return None,
+            };
+            // Strip symbol version, e.g. `@GLIBC_2.2.5`
+            let name = entry[7].split('@').next()?;
+            Some((name.to_string(), ty))
+        })
+        .collect();
+    symbols.sort();
+    symbols.dedup(); // both .dynsym and .symtab are shown
+    symbols
+}
+
+/// Parse external symbols from `dumpbin /SYMBOLS` output into `nm` symbol types
+///
+/// assumes `dumpbin /SYMBOLS` output like following:
+///
+/// ```text
+/// 008 00000000 SECT3  notype ()    External     | dgemm_
+/// 00A 00000000 UNDEF  notype ()    External     | pthread_create
+/// ```
+fn parse_dumpbin_symbols(stdout: &[u8]) -> Vec<(String, char)> {
+    let mut symbols: Vec<_> = String::from_utf8_lossy(stdout)
+        .lines()
+        .flat_map(|line| {
+            let (attrs, name) = line.split_once('|')?;
+            let attrs: Vec<_> = attrs.split_whitespace().collect();
+            if attrs.len() < 4 || attrs.last() != Some(&"External") {
+                return None;
+            }
+            let ty = if attrs[2] == "UNDEF" {
+                'U'
+            } else if attrs.contains(&"()") {
+                'T'
+            } else {
+                'D'
+            };
+            // Strip undecorated name, e.g. `_dgemm_ (dgemm_)`
+            let name = name.split_whitespace().next()?;
+            Some((name.to_string(), ty))
+        })
+        .collect();
+    symbols.sort();
+    symbols
+}
+
+/// Parse dependent DLLs from `dumpbin /DEPENDENTS` output
+///
+/// assumes `dumpbin /DEPENDENTS` output like following:
+///
+/// ```text
+///   Image has the following dependencies:
+///
+///     libgfortran-5.dll
+///     KERNEL32.dll
+///
+///   Summary
+/// ```
+fn parse_dumpbin_dependents(stdout: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .map(|line| line.trim())
+        .skip_while(|line| !line.starts_with("Image has the following dependencies"))
+        .skip(1)
+        .take_while(|line| *line != "Summary" && !line.starts_with("Image has the following"))
+        .filter(|line| !line.is_empty())
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Parse resolved dependencies from `ldd` output, `None` for "not found"
+///
+/// assumes `ldd` output like following:
+///
+/// ```text
+///         linux-vdso.so.1 (0x00007ffc8d5e2000)
+///         libgfortran.so.5 => /lib/x86_64-linux-gnu/libgfortran.so.5 (0x00007f2d4c000000)
+///         libfoo.so.1 => not found
+///         /lib64/ld-linux-x86-64.so.2 (0x00007f2d4e8b0000)
+/// ```
+///
+/// Entries without `=>`, i.e. the vDSO and the loader, are skipped.
+fn parse_ldd(stdout: &[u8]) -> Vec<(String, Option<PathBuf>)> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .flat_map(|line| {
+            let (name, resolved) = line.split_once("=>")?;
+            let resolved = resolved.trim();
+            let path = if resolved == "not found" {
+                None
+            } else {
+                let path = resolved.split(" (").next()?.trim();
+                Some(PathBuf::from(path))
+            };
+            Some((name.trim().to_string(), path))
+        })
+        .collect()
+}
+
+/// Find bitness from the file format in `objdump -f` output
+///
+/// assumes `objdump -f` output like following:
+///
+/// ```text
+/// libopenblas.so:     file format elf64-x86-64
+/// architecture: i386:x86-64, flags 0x00000150:
+/// ```
+///
+/// For static archives, the format of the first member is used.
+fn parse_objdump_format(stdout: &[u8]) -> Option<Bitness> {
+    for line in String::from_utf8_lossy(stdout).lines() {
+        let format = match line.split_once("file format") {
+            Some((_, format)) => format.trim(),
+            None => continue,
+        };
+        // ELF has the class in its name, e.g. `elf32-x86-64` for x32 ABI
+        if format.starts_with("elf64-") {
+            return Some(Bitness::Bits64);
+        }
+        if format.starts_with("elf32-") {
+            return Some(Bitness::Bits32);
+        }
+        // PE and Mach-O have the architecture in its name, e.g. `pei-x86-64` or `mach-o-i386`
+        let arch = ["pe-bigobj-", "pei-", "pe-", "mach-o-"]
+            .iter()
+            .find_map(|prefix| format.strip_prefix(prefix));
+        if let Some(arch) = arch {
+            return Some(if arch.contains("64") {
+                Bitness::Bits64
+            } else {
+                Bitness::Bits32
+            });
+        }
+    }
+    None
+}
+
+/// Find the DLL name which an import library refers from `dumpbin /HEADERS` output
+///
+/// assumes `dumpbin /HEADERS` output of an import library like following:
+///
+/// ```text
+///   Version      : 0
+///   Machine      : 8664 (x64)
+///   SizeOfData   : 00000020
+///   DLL name     : libopenblas.dll
+///   Symbol name  : dgemm_
+/// ```
+fn parse_dumpbin_import_dll(stdout: &[u8]) -> Option<String> {
+    for line in String::from_utf8_lossy(stdout).lines() {
+        if let Some((key, value)) = line.split_once(':') {
+            if key.trim() == "DLL name" && !value.trim().is_empty() {
+                return Some(value.trim().into());
+            }
+        }
+    }
+    None
+}
+
+/// Find version like `0.3.14` from `strings` output including `OpenBLAS 0.3.14`
+fn parse_version(stdout: &[u8]) -> Option<String> {
+    for line in String::from_utf8_lossy(stdout).lines() {
+        let mut words = line.split_whitespace();
+        while let Some(word) = words.next() {
+            if word != "OpenBLAS" {
+                continue;
+            }
+            match words.next() {
+                Some(version) if version.starts_with(|c: char| c.is_ascii_digit()) => {
+                    return Some(version.into())
+                }
+                _ => continue,
+            }
+        }
+    }
+    None
+}
+
+/// Running binutil command
+struct RunningTool {
+    tool: String,
+    child: Child,
+}
+
+/// Read all of the pipe in another thread, since a tool blocks when the pipe is full
+fn read_in_thread<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buf = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buf);
+        }
+        buf
+    })
+}
+
+/// Kill the tool not waited, e.g. a sibling of the failed one, so that it does not remain as a zombie
+impl Drop for RunningTool {
+    fn drop(&mut self) {
+        // These do nothing if the tool has already exited and been waited
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+impl RunningTool {
+    /// Wait for exit, and fail if it exits with non-zero status, e.g. "File format not recognized"
+    ///
+    /// The tool is killed if it does not exit in `timeout`, e.g. on a hung filesystem.
+    fn wait(mut self, timeout: Duration) -> Result<Output, Error> {
+        let tool = std::mem::take(&mut self.tool);
+        let stdout = read_in_thread(self.child.stdout.take());
+        let stderr = read_in_thread(self.child.stderr.take());
+        let deadline = Instant::now() + timeout;
+        let status = loop {
+            match self.child.try_wait() {
+                Ok(Some(status)) => break status,
+                Ok(None) if Instant::now() >= deadline => {
+                    return Err(Error::BinutilTimeout { tool });
+                }
+                Ok(None) => thread::sleep(Duration::from_millis(10)),
+                Err(source) => return Err(Error::BinutilTool { tool, source }),
+            }
+        };
+        let output = Output {
+            status,
+            stdout: stdout.join().unwrap_or_default(),
+            stderr: stderr.join().unwrap_or_default(),
+        };
+        if !output.status.success() {
+            return Err(Error::BinutilFailed {
+                tool,
+                status: output.status,
+                stderr: String::from_utf8_lossy(&output.stderr).trim().into(),
+            });
+        }
+        Ok(output)
+    }
+}
+
+/// Start binutil command, and report which tool failed to start
+fn spawn_tool(command: &mut Command) -> Result<RunningTool, Error> {
+    let tool = command.get_program().to_string_lossy().to_string();
+    match command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => Ok(RunningTool { tool, child }),
+        Err(source) => Err(Error::BinutilTool { tool, source }),
+    }
+}
+
+/// Parse linked libraries from `otool -L` output
+///
+/// assumes `otool -L` output like following:
+///
+/// ```text
+/// /usr/local/opt/openblas/lib/libopenblas.dylib:
+///         /usr/local/opt/gcc/lib/gcc/10/libgfortran.5.dylib (compatibility version 6.0.0, current version 6.0.0)
+/// ```
+fn parse_otool(stdout: &[u8]) -> Vec<String> {
+    String::from_utf8_lossy(stdout)
+        .lines()
+        .skip(1) // first line is the inspected file itself
+        .flat_map(|line| {
+            let path = line.trim().split(" (").next()?;
+            let name = Path::new(path).file_name()?;
+            Some(name.to_string_lossy().to_string())
+        })
+        .collect()
+}
+
+/// Pointer width of object files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Bitness {
+    Bits32,
+    Bits64,
+}
+
+/// Threading model of OpenBLAS
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ThreadingModel {
+    /// Single-threaded build, i.e. `USE_THREAD=0`
+    Sequential,
+    /// Built with pthreads, i.e. `USE_THREAD=1`
+    Pthread,
+    /// Built with OpenMP, i.e. `USE_OPENMP=1`
+    OpenMP,
+}
+
+/// Default timeout of `nm` and `objdump` in [LibInspect], see [LibInspect::with_timeout]
+pub const DEFAULT_BINUTIL_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Size which a stripped OpenBLAS rarely exceeds, see [LibInspect::is_probably_unstripped]
+///
+/// A stripped `libopenblas.so` is a few tens of MB even with DYNAMIC_ARCH.
+pub const UNSTRIPPED_SIZE_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Average bytes per symbol which a stripped OpenBLAS rarely exceeds, see [LibInspect::is_probably_unstripped]
+pub const UNSTRIPPED_BYTES_PER_SYMBOL: u64 = 16 * 1024;
+
+/// Minimum number of "T" symbols which a complete OpenBLAS exports, see [LibInspect::looks_complete]
+///
+/// Even a build without LAPACK exports BLAS routines in Fortran and C interfaces more than this.
+pub const MIN_SYMBOL_COUNT: usize = 500;
+
+/// Difference of two [LibInspect] results, see [LibInspect::diff]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolDiff {
+    /// "T" symbols only in the new library, sorted alphabetically
+    pub added: Vec<String>,
+    /// "T" symbols only in the old library, sorted alphabetically
+    pub removed: Vec<String>,
+    /// Linked libraries only in the new library
+    pub added_libs: Vec<String>,
+    /// Linked libraries only in the old library
+    pub removed_libs: Vec<String>,
+}
+
+impl SymbolDiff {
+    /// Both libraries have the same symbols and linked libraries
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.added_libs.is_empty()
+            && self.removed_libs.is_empty()
+    }
+}
+
+/// Entries only in `new` and only in `old`, both of them must be sorted
+fn sorted_diff(old: &[String], new: &[String]) -> (Vec<String>, Vec<String>) {
+    let (mut added, mut removed) = (Vec::new(), Vec::new());
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() && j < new.len() {
+        match old[i].cmp(&new[j]) {
+            std::cmp::Ordering::Less => {
+                removed.push(old[i].clone());
+                i += 1;
+            }
+            std::cmp::Ordering::Greater => {
+                added.push(new[j].clone());
+                j += 1;
+            }
+            std::cmp::Ordering::Equal => {
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    removed.extend_from_slice(&old[i..]);
+    added.extend_from_slice(&new[j..]);
+    (added, removed)
+}
+
+/// Which library provides LAPACK routines
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LapackProvider {
+    /// LAPACK is built into the library
+    OpenBlasBuiltin,
+    /// LAPACK is provided by a linked library, e.g. `liblapack.so.3`
+    ExternalNeeded(String),
+    /// LAPACK is not available
+    None,
+}
+
+/// Which interfaces of LAPACK are built, see [LibInspect::lapack_completeness]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LapackCompleteness {
+    /// Both Fortran LAPACK and LAPACKE C interface
+    Full,
+    /// Only LAPACKE C interface, e.g. `LAPACKE_dsyev`
+    LapackeOnly,
+    /// Only Fortran LAPACK, e.g. `dsyev_`
+    FortranOnly,
+    /// Neither of them
+    None,
+}
+
+impl LibInspect {
+    /// Inspect library file
+    ///
+    /// Be sure that `nm -g` and `objdump -p` are executed in this function.
+    /// These commands can be replaced by `NM` and `OBJDUMP` environment variables.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        let nm = env::var("NM").unwrap_or_else(|_| "nm".into());
+        let objdump = env::var("OBJDUMP").unwrap_or_else(|_| "objdump".into());
+        Self::with_tools(path, &nm, &objdump)
+    }
+
+    /// Inspect library file, but retain only symbols starting with one of `prefixes`
+    ///
+    /// This reduces memory for a huge static archive. Predicates work as far as their symbols are retained,
+    /// e.g. `["dgemm_", "dsyev_", "cblas_", "LAPACKE_"]` keeps [LibInspect::has_lapack] and so on working.
+    ///
+    /// ```no_run
+    /// use openblas_build::*;
+    /// let lib = LibInspect::new_filtered("libopenblas.a", &["dsyev_", "LAPACKE_"]).unwrap();
+    /// assert!(lib.has_lapack());
+    /// ```
+    pub fn new_filtered<P: AsRef<Path>>(path: P, prefixes: &[&str]) -> Result<Self, Error> {
+        let nm = env::var("NM").unwrap_or_else(|_| "nm".into());
+        let objdump = env::var("OBJDUMP").unwrap_or_else(|_| "objdump".into());
+        Self::inspect(
+            path.as_ref(),
+            &nm,
+            &objdump,
+            Some(prefixes),
+            DEFAULT_BINUTIL_TIMEOUT,
+        )
+    }
+
+    /// Find and inspect `libopenblas.{a,so,so.*,dylib,dll}` in the directory, e.g. the output of `make`
+    ///
+    /// Shared libraries are preferred to static archives,
+    /// and a library having symbols is preferred to a stripped one.
+    /// Candidates which cannot be inspected are skipped.
+    pub fn from_dir<P: AsRef<Path>>(dir: P) -> Result<Self, Error> {
+        let dir = dir.as_ref();
+        let mut shared = Vec::new();
+        let mut archives = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            let name = match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) => name,
+                None => continue,
+            };
+            let ext = match name.strip_prefix("libopenblas.") {
+                Some(ext) => ext,
+                None => continue,
+            };
+            if ext == "a" {
+                archives.push(path);
+            } else if ext == "so" || ext.starts_with("so.") || ext == "dylib" || ext == "dll" {
+                shared.push(path);
+            }
+        }
+        shared.sort();
+        archives.sort();
+
+        let mut stripped = None;
+        let mut error = None;
+        for path in shared.into_iter().chain(archives) {
+            match Self::new(&path) {
+                Ok(lib) if !lib.is_stripped() => return Ok(lib),
+                Ok(lib) => {
+                    stripped.get_or_insert(lib);
+                }
+                Err(e) => {
+                    error.get_or_insert(e);
+                }
+            }
+        }
+        match (stripped, error) {
+            (Some(lib), _) => Ok(lib),
+            (None, Some(e)) => Err(e),
+            (None, None) => Err(Error::LibraryNotExist {
+                path: dir.join("libopenblas.*"),
+            }),
+        }
+    }
+
+    /// Inspect library file using specified `nm` and `objdump`,
+    /// e.g. `aarch64-linux-gnu-nm` and `aarch64-linux-gnu-objdump` for cross compiling
+    pub fn with_tools<P: AsRef<Path>>(path: P, nm: &str, objdump: &str) -> Result<Self, Error> {
+        Self::inspect(path.as_ref(), nm, objdump, None, DEFAULT_BINUTIL_TIMEOUT)
+    }
+
+    /// Inspect library file using specified `nm` and `objdump`, and kill them if they do not exit in `timeout`
+    ///
+    /// [LibInspect::with_tools] uses [DEFAULT_BINUTIL_TIMEOUT].
+    pub fn with_timeout<P: AsRef<Path>>(
+        path: P,
+        nm: &str,
+        objdump: &str,
+        timeout: Duration,
+    ) -> Result<Self, Error> {
+        Self::inspect(path.as_ref(), nm, objdump, None, timeout)
+    }
+
+    fn inspect(
+        path: &Path,
+        nm: &str,
+        objdump: &str,
+        prefixes: Option<&[&str]>,
+        timeout: Duration,
+    ) -> Result<Self, Error> {
+        if !path.exists() {
+            return Err(Error::LibraryNotExist {
+                path: path.to_owned(),
+            });
+        }
+
+        let is_static = is_archive(path)?;
+
+        // Spawn all commands first to run them concurrently
+        type Parser<T> = fn(&[u8]) -> Vec<T>;
+        type NeededParsers = (Parser<String>, Parser<PathBuf>);
+        let (symbols, parse_symbols, needed): (_, Parser<_>, Option<(_, NeededParsers)>) =
+            if cfg!(windows) {
+                // binutils are not available with MSVC toolchain
+                let symbols = spawn_tool(Command::new("dumpbin").arg("/SYMBOLS").arg(path))?;
+                let dependents = spawn_tool(Command::new("dumpbin").arg("/DEPENDENTS").arg(path))?;
+                (
+                    symbols,
+                    parse_dumpbin_symbols,
+                    Some((dependents, (parse_dumpbin_dependents, no_runpaths))),
+                )
+            } else {
+                // Fallback to `readelf` if `nm` or `objdump` is not installed
+                let readelf = readelf_for(objdump);
+                let (symbols, parse_symbols): (_, Parser<_>) =
+                    match spawn_tool(Command::new(nm).arg("-g").arg(path)) {
+                        Ok(child) => (child, parse_nm),
+                        Err(err) => (
+                            spawn_tool(Command::new(&readelf).arg("-W").arg("--syms").arg(path))
+                                .map_err(|_| err)?,
+                            parse_readelf_syms,
+                        ),
+                    };
+                let needed: Option<(_, NeededParsers)> = if is_static {
+                    None
+                } else if cfg!(target_os = "macos") {
+                    // `objdump -p` does not show NEEDED for Mach-O
+                    Some((
+                        spawn_tool(Command::new("otool").arg("-L").arg(path))?,
+                        (parse_otool, no_runpaths),
+                    ))
+                } else {
+                    Some(
+                        match spawn_tool(Command::new(objdump).arg("-p").arg(path)) {
+                            Ok(child) => (child, (parse_objdump_needed, parse_objdump_runpaths)),
+                            Err(err) => (
+                                spawn_tool(Command::new(&readelf).arg("-d").arg(path))
+                                    .map_err(|_| err)?,
+                                (parse_readelf_needed, parse_readelf_runpaths),
+                            ),
+                        },
+                    )
+                };
+                (symbols, parse_symbols, needed)
+            };
+
+        let mut typed_symbols = parse_symbols(&symbols.wait(timeout)?.stdout);
+        if is_mach_o(path)? {
+            // `_dsyev_` in Mach-O is `dsyev_` in C and Fortran
+            for (sym, _) in &mut typed_symbols {
+                if let Some(stripped) = sym.strip_prefix('_') {
+                    *sym = stripped.to_string();
+                }
+            }
+            typed_symbols.sort();
+        }
+        if let Some(prefixes) = prefixes {
+            typed_symbols.retain(|(sym, _)| prefixes.iter().any(|prefix| sym.starts_with(prefix)));
+            typed_symbols.shrink_to_fit();
+        }
+        let (libs, runpaths) = match needed {
+            Some((child, (parse_libs, parse_runpaths))) => {
+                let stdout = child.wait(timeout)?.stdout;
+                (parse_libs(&stdout), parse_runpaths(&stdout))
+            }
+            None => (Vec::new(), Vec::new()),
+        };
+        Ok(LibInspect {
+            runpaths,
+            objdump: objdump.into(),
+            timeout,
+            ..Self::from_parts(path, is_static, typed_symbols, libs)
+        })
+    }
+
+    /// Parse outputs of `nm -g` and `objdump -p` without executing them
+    ///
+    /// This is useful to cache outputs of these commands.
+    /// [LibInspect::path] is empty since the library is not specified.
+    ///
+    /// ```
+    /// use openblas_build::*;
+    /// let lib = LibInspect::from_outputs(
+    ///     b"0000000000909b30 T dsyev_\n",
+    ///     b"  NEEDED               libgfortran.so.5\n",
+    /// );
+    /// assert!(lib.has_lapack());
+    /// assert!(lib.has_lib("gfortran"));
+    /// ```
+    pub fn from_outputs(nm_stdout: &[u8], objdump_stdout: &[u8]) -> Self {
+        LibInspect {
+            runpaths: parse_objdump_runpaths(objdump_stdout),
+            ..Self::from_parts(
+                Path::new(""),
+                false,
+                parse_nm(nm_stdout),
+                parse_objdump_needed(objdump_stdout),
+            )
+        }
+    }
+
+    fn from_parts(
+        path: &Path,
+        is_static: bool,
+        typed_symbols: Vec<(String, char)>,
+        mut libs: Vec<String>,
+    ) -> Self {
+        let symbols = typed_symbols
+            .iter()
+            .filter(|(_, ty)| *ty == 'T')
+            .map(|(sym, _)| sym.clone())
+            .collect();
+        libs.sort();
+        LibInspect {
+            path: path.into(),
+            real_path: path.canonicalize().unwrap_or_else(|_| path.into()),
+            is_static,
+            libs,
+            symbols,
+            typed_symbols,
+            size_bytes: fs::metadata(path).map_or(0, |meta| meta.len()),
+            runpaths: Vec::new(),
+            objdump: env::var("OBJDUMP").unwrap_or_else(|_| "objdump".into()),
+            timeout: DEFAULT_BINUTIL_TIMEOUT,
+        }
+    }
+
+    /// Inspect library file, and cache the result in `cache_dir`
+    ///
+    /// The cache is keyed by the path of library, and invalidated when its mtime or size changes.
+    /// It is a plain text file, one entry per line:
+    ///
+    /// ```text
+    /// mtime 1617408000.123456789
+    /// size 35462480
+    /// static 0
+    /// lib libgfortran.so.5
+    /// sym T dgemm_
+    /// end 3
+    /// ```
+    ///
+    /// The last line counts the entries after `size`, so a truncated file is not used.
+    /// Plain text instead of JSON keeps this available without the optional `serde` feature.
+    pub fn new_cached<P: AsRef<Path>, Q: AsRef<Path>>(
+        path: P,
+        cache_dir: Q,
+    ) -> Result<Self, Error> {
+        Self::cached(path.as_ref(), cache_dir.as_ref(), |path| Self::new(path))
+    }
+
+    fn cached(
+        path: &Path,
+        cache_dir: &Path,
+        inspect: impl FnOnce(&Path) -> Result<Self, Error>,
+    ) -> Result<Self, Error> {
+        use std::time::UNIX_EPOCH;
+
+        let meta = fs::metadata(path).map_err(|_| Error::LibraryNotExist {
+            path: path.to_owned(),
+        })?;
+        let mtime = meta
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let header = format!(
+            "mtime {}.{:09}\nsize {}\n",
+            mtime.as_secs(),
+            mtime.subsec_nanos(),
+            meta.len()
+        );
+
+        // The hash must be stable across Rust versions since the cache outlives the build script
+        let name = format!("{:016x}", fnv1a(path.to_string_lossy().as_bytes()));
+        let cache = cache_dir.join(format!("{}.txt", name));
+
+        if let Ok(content) = fs::read_to_string(&cache) {
+            if let Some(body) = content.strip_prefix(&header) {
+                if let Some(lib) = Self::from_cache(path, body) {
+                    return Ok(lib);
+                }
+            }
+        }
+
+        let lib = inspect(path)?;
+        let mut body = format!("static {}\n", lib.is_static as u8);
+        for name in &lib.libs {
+            body.push_str(&format!("lib {}\n", name));
+        }
+        for dir in &lib.runpaths {
+            body.push_str(&format!("runpath {}\n", dir.display()));
+        }
+        for (sym, ty) in &lib.typed_symbols {
+            body.push_str(&format!("sym {} {}\n", ty, sym));
+        }
+        let content = format!("{}{}end {}\n", header, body, body.lines().count());
+
+        // Write to a temporary file and rename it, so that other processes never read a partial file
+        fs::create_dir_all(cache_dir)?;
+        let tmp = cache_dir.join(format!(
+            "{}.{}-{}.tmp",
+            name,
+            std::process::id(),
+            TEMP_DIR_COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&tmp, content)?;
+        if let Err(e) = fs::rename(&tmp, &cache) {
+            let _ = fs::remove_file(&tmp);
+            return Err(e.into());
+        }
+        Ok(lib)
+    }
+
+    /// Restore from cache file written by [LibInspect::new_cached], `None` if broken
+    fn from_cache(path: &Path, body: &str) -> Option<Self> {
+        let mut is_static = None;
+        let mut libs = Vec::new();
+        let mut runpaths = Vec::new();
+        let mut typed_symbols = Vec::new();
+        let mut entries = 0;
+        let mut end = None;
+        for line in body.lines() {
+            if end.is_some() {
+                return None;
+            }
+            let (key, value) = line.split_once(' ')?;
+            if key != "end" {
+                entries += 1;
+            }
+            match key {
+                "end" => end = Some(value.parse::<usize>().ok()?),
+                "static" => is_static = Some(value == "1"),
+                "lib" => libs.push(value.to_string()),
+                "runpath" => runpaths.push(PathBuf::from(value)),
+                "sym" => {
+                    let (ty, sym) = value.split_once(' ')?;
+                    typed_symbols.push((sym.to_string(), ty.chars().next()?));
+                }
+                _ => return None,
+            }
+        }
+        if end? != entries {
+            return None;
+        }
+        Some(LibInspect {
+            runpaths,
+            ..Self::from_parts(path, is_static?, typed_symbols, libs)
+        })
+    }
+
+    /// Path of the inspected library
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Files read to create this, e.g. for `cargo:rerun-if-changed`
+    ///
+    /// Empty if created by [LibInspect::from_outputs].
+    pub fn deps(&self) -> Vec<PathBuf> {
+        if self.path.as_os_str().is_empty() {
+            Vec::new()
+        } else {
+            vec![self.path.clone()]
+        }
+    }
+
+    /// Canonicalized [LibInspect::path] with symlinks resolved,
+    /// e.g. `libopenblas.so.0.3.21` for `libopenblas.so` → `libopenblas.so.0` → `libopenblas.so.0.3.21`
+    pub fn real_path(&self) -> &Path {
+        &self.real_path
+    }
+
+    /// The inspected library is a static archive, e.g. `libopenblas.a`
+    pub fn is_static(&self) -> bool {
+        self.is_static
+    }
+
+    /// The library does not have any defined global symbols, e.g. stripped by `strip --strip-all`
+    ///
+    /// `has_*` predicates always return `false` for stripped library.
+    pub fn is_stripped(&self) -> bool {
+        self.typed_symbols.iter().all(|(_, ty)| *ty == 'U')
+    }
+
+    /// Library is so large for its symbols that it likely contains debug info, i.e. should be stripped
+    ///
+    /// This is the case if it is larger than [UNSTRIPPED_SIZE_BYTES],
+    /// and more than [UNSTRIPPED_BYTES_PER_SYMBOL] per symbol in average.
+    pub fn is_probably_unstripped(&self) -> bool {
+        let symbols = self.typed_symbols.len().max(1) as u64;
+        self.size_bytes > UNSTRIPPED_SIZE_BYTES
+            && self.size_bytes / symbols > UNSTRIPPED_BYTES_PER_SYMBOL
+    }
+
+    /// Symbols in text section including weak symbols, i.e. `T` and `W`
+    fn exported_symbols(&self) -> impl Iterator<Item = &str> {
+        self.typed_symbols
+            .iter()
+            .filter(|(_, ty)| matches!(ty, 'T' | 'W'))
+            .map(|(sym, _)| sym.as_str())
+    }
+
+    /// Check CBLAS routine exists, e.g. `cblas_dgemm`
+    ///
+    /// `cblas_xerbla` is ignored since some incomplete builds export only it.
+    pub fn has_cblas(&self) -> bool {
+        for sym in self.exported_symbols() {
+            match sym.strip_prefix("cblas_") {
+                Some(routine) if !routine.starts_with("xerbla") => return true,
+                _ => continue,
+            }
+        }
+        false
+    }
+
+    /// Check LAPACK symbol i.e. `dsyev_` exists, or `dsyev_64_` with ILP64 symbol suffix
+    pub fn has_lapack(&self) -> bool {
+        let suffix = self.symbol_suffix().unwrap_or_default();
+        for sym in self.exported_symbols() {
+            if sym == "dsyev_" || sym.strip_prefix("dsyev_") == Some(suffix.as_str()) {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Check core BLAS kernels `dgemm_`, `sgemm_`, and `daxpy_` all exist, with ILP64 symbol suffix if any
+    ///
+    /// Stronger than [LibInspect::has_cblas] which only checks the C wrappers.
+    pub fn has_blas(&self) -> bool {
+        let suffix = self.symbol_suffix().unwrap_or_default();
+        ["dgemm_", "sgemm_", "daxpy_"].iter().all(|kernel| {
+            self.exported_symbols()
+                .any(|sym| sym == *kernel || sym.strip_prefix(kernel) == Some(suffix.as_str()))
+        })
+    }
+
+    /// Detect symbol suffix, e.g. `64_` of `dgemm_64_` or `cblas_dgemm64_` in ILP64 builds
+    ///
+    /// The suffix must end with `_` and be shared by `dgemm_`, `sgemm_`, and `daxpy_`, or their CBLAS wrappers,
+    /// so that internal kernels like `dgemm_beta` or extensions like `cblas_dgemmt` are not mistaken for it.
+    /// `None` if symbols do not have a suffix.
+    pub fn symbol_suffix(&self) -> Option<String> {
+        let exported: HashSet<&str> = self.exported_symbols().collect();
+        let shared_by = |routines: [&str; 3]| {
+            exported
+                .iter()
+                .filter_map(|sym| sym.strip_prefix(routines[0]))
+                .filter(|suffix| suffix.len() > 1 && suffix.ends_with('_'))
+                .find(|suffix| {
+                    routines[1..]
+                        .iter()
+                        .all(|routine| exported.contains(format!("{}{}", routine, suffix).as_str()))
+                })
+                .map(|suffix| suffix.to_string())
+        };
+        shared_by(["dgemm_", "sgemm_", "daxpy_"])
+            .or_else(|| shared_by(["cblas_dgemm", "cblas_sgemm", "cblas_daxpy"]))
+    }
+
+    /// Check LAPACKE symbols exist, e.g. `LAPACKE_dsyev` or lowercase `lapacke_dsyev`
+    pub fn has_lapacke(&self) -> bool {
+        for sym in self.exported_symbols() {
+            if sym
+                .get(..8)
+                .is_some_and(|stem| stem.eq_ignore_ascii_case("lapacke_"))
+            {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Check the library has a global "T" symbol exactly matching `name`
+    pub fn has_symbol(&self, name: &str) -> bool {
+        self.symbols
+            .binary_search_by(|sym| sym.as_str().cmp(name))
+            .is_ok()
+    }
+
+    /// Check the library has a global "T" symbol starting with `prefix`
+    pub fn has_symbol_prefix(&self, prefix: &str) -> bool {
+        let pos = self.symbols.partition_point(|sym| sym.as_str() < prefix);
+        self.symbols
+            .get(pos)
+            .is_some_and(|sym| sym.starts_with(prefix))
+    }
+
+    /// Detect threading model from symbols and linked libraries
+    ///
+    /// - OpenMP if it uses `GOMP_parallel` or links an OpenMP runtime, see [LibInspect::openmp_runtime]
+    /// - pthreads if it uses `pthread_create` or links `libpthread`
+    /// - Otherwise sequential
+    pub fn threading(&self) -> ThreadingModel {
+        let uses = |name: &str| self.typed_symbols.iter().any(|(sym, _)| sym == name);
+        if uses("GOMP_parallel") || self.openmp_runtime().is_some() {
+            ThreadingModel::OpenMP
+        } else if uses("pthread_create") || self.has_lib("pthread") {
+            ThreadingModel::Pthread
+        } else {
+            ThreadingModel::Sequential
+        }
+    }
+
+    /// Check Fortran LAPACK and LAPACKE C interface independently, see [LibInspect::has_lapack] and [LibInspect::has_lapacke]
+    pub fn lapack_completeness(&self) -> LapackCompleteness {
+        match (self.has_lapack(), self.has_lapacke()) {
+            (true, true) => LapackCompleteness::Full,
+            (false, true) => LapackCompleteness::LapackeOnly,
+            (true, false) => LapackCompleteness::FortranOnly,
+            (false, false) => LapackCompleteness::None,
+        }
+    }
+
+    /// Check LAPACK is built into OpenBLAS, or provided by a linked library like `liblapack.so.3`
+    pub fn lapack_provider(&self) -> LapackProvider {
+        if self.has_lapack() {
+            return LapackProvider::OpenBlasBuiltin;
+        }
+        match self.libs.iter().find(|lib| lib_name(lib) == "lapack") {
+            Some(lib) => LapackProvider::ExternalNeeded(lib.clone()),
+            None => LapackProvider::None,
+        }
+    }
+
+    /// CPU targets whose kernels are compiled in `DYNAMIC_ARCH` build, e.g. `HASWELL` of `dgemm_kernel_HASWELL`
+    ///
+    /// Targets are detected by the suffix of defined symbols which is an uppercase name in [crate::Target].
+    /// Sorted alphabetically, and empty if not `DYNAMIC_ARCH` build.
+    pub fn dynamic_arch_targets(&self) -> Vec<String> {
+        let mut targets = Vec::new();
+        for (sym, _) in self.typed_symbols.iter().filter(|(_, ty)| *ty != 'U') {
+            // Check one or two last segments, e.g. `HASWELL` or `SSE_GENERIC`
+            let segments: Vec<&str> = sym.rsplitn(3, '_').collect();
+            let candidates = [
+                segments.first().map(|last| last.to_string()),
+                segments
+                    .get(1)
+                    .map(|prev| format!("{}_{}", prev, segments[0])),
+            ];
+            for tag in candidates.iter().flatten() {
+                let is_target = tag.chars().any(|c| c.is_ascii_uppercase())
+                    && !tag.chars().any(|c| c.is_ascii_lowercase())
+                    && tag.parse::<crate::Target>().is_ok();
+                if is_target && segments.len() > 1 && !targets.contains(tag) {
+                    targets.push(tag.clone());
+                }
+            }
+        }
+        targets.sort();
+        targets
+    }
+
+    /// OpenMP runtime linked to the library, `libgomp` (GCC), `libomp` (LLVM), or `libiomp5` (Intel)
+    ///
+    /// Linking different OpenMP runtimes into one binary may cause crashes.
+    pub fn openmp_runtime(&self) -> Option<String> {
+        ["gomp", "omp", "iomp5"]
+            .iter()
+            .find(|name| self.has_lib(name))
+            .map(|name| format!("lib{}", name))
+    }
+
+    /// Execute an external command about the library with the timeout of inspection
+    fn run(&self, command: &mut Command) -> Result<Output, Error> {
+        spawn_tool(command)?.wait(self.timeout)
+    }
+
+    /// Pointer width of the library, e.g. to check it matches `CARGO_CFG_TARGET_POINTER_WIDTH`
+    ///
+    /// This executes `objdump -f` external command, which is specified by [LibInspect::with_tools],
+    /// or `OBJDUMP` environment variable.
+    /// `None` if `objdump` is not available, or the file format is unknown.
+    pub fn bitness(&self) -> Option<Bitness> {
+        let out = self
+            .run(Command::new(&self.objdump).arg("-f").arg(&self.path))
+            .ok()?;
+        parse_objdump_format(&out.stdout)
+    }
+
+    /// Paths which `ldd` resolves linked libraries to, e.g. `("libm.so.6", "/lib/x86_64-linux-gnu/libm.so.6")`
+    ///
+    /// This runs `ldd` every time, and may differ from [LibInspect::libs] at runtime by `LD_LIBRARY_PATH`.
+    ///
+    /// Error
+    /// -----
+    /// - `ldd` fails, e.g. for a static archive
+    /// - A linked library is not found
+    #[cfg(target_os = "linux")]
+    pub fn resolved_deps(&self) -> Result<Vec<(String, PathBuf)>, Error> {
+        let output = self.run(Command::new("ldd").arg(&self.path))?;
+        parse_ldd(&output.stdout)
+            .into_iter()
+            .map(|(name, path)| match path {
+                Some(path) => Ok((name, path)),
+                None => Err(Error::DependencyNotFound { name }),
+            })
+            .collect()
+    }
+
+    /// DLL name which the import library refers, e.g. `libopenblas.dll` for `libopenblas.lib` on Windows
+    ///
+    /// This executes `dumpbin /HEADERS` external command.
+    /// `None` if `dumpbin` is not available, or the library is not an import library.
+    pub fn import_dll(&self) -> Option<String> {
+        let out = self
+            .run(Command::new("dumpbin").arg("/HEADERS").arg(&self.path))
+            .ok()?;
+        parse_dumpbin_import_dll(&out.stdout)
+    }
+
+    /// OpenBLAS version, e.g. `0.3.14`
+    ///
+    /// This does not execute `openblas_get_config` in the library,
+    /// but searches the string `OpenBLAS 0.3.14` embedded in it using `strings` external command.
+    /// `None` if the library does not have `openblas_get_config` symbol, or the version string is not found.
+    pub fn version(&self) -> Option<String> {
+        if !self.has_symbol("openblas_get_config") {
+            return None;
+        }
+        let out = self
+            .run(Command::new("strings").arg("-a").arg(&self.path))
+            .ok()?;
+        parse_version(&out.stdout)
+    }
+
+    /// Number of global "T" symbols
+    pub fn symbol_count(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Heuristic check that the build is not truncated,
+    /// i.e. more than [MIN_SYMBOL_COUNT] symbols and core BLAS exist
+    pub fn looks_complete(&self) -> bool {
+        self.symbol_count() > MIN_SYMBOL_COUNT && self.has_blas()
+    }
+
+    /// Symbols and linked libraries added in `other` or removed from `self`, e.g. to check ABI compatibility
+    pub fn diff(&self, other: &LibInspect) -> SymbolDiff {
+        let (added, removed) = sorted_diff(&self.symbols, &other.symbols);
+        let (added_libs, removed_libs) = sorted_diff(&self.libs, &other.libs);
+        SymbolDiff {
+            added,
+            removed,
+            added_libs,
+            removed_libs,
+        }
+    }
+
+    /// RPATH and RUNPATH entries embedded in the shared library, e.g. `/opt/OpenBLAS/lib` or `$ORIGIN/../lib`
+    ///
+    /// Absolute paths make the library not relocatable. These are not read for Mach-O and Windows DLL.
+    pub fn runpaths(&self) -> &[PathBuf] {
+        &self.runpaths
+    }
+
+    /// Library has absolute run-time paths, i.e. it is not relocatable, see [LibInspect::runpaths]
+    pub fn has_absolute_runpath(&self) -> bool {
+        self.runpaths.iter().any(|dir| dir.is_absolute())
+    }
+
+    /// Check the library links `name`, e.g. `gfortran` for `libgfortran.so.5`
+    ///
+    /// DLL names are compared case-insensitively, e.g. `msvcrt` matches `MSVCRT.dll`.
+    pub fn has_lib(&self, name: &str) -> bool {
+        self.libs.iter().any(|lib| {
+            let lib_name = lib_name(lib);
+            lib_name == name || (is_dll(lib) && lib_name.eq_ignore_ascii_case(name))
+        })
+    }
+
+    /// Linked libraries as `-l` flags, e.g. `-lgfortran` for `libgfortran.so.5`, to link them again
+    ///
+    /// The dynamic loader, e.g. `ld-linux-x86-64.so.2`, is excluded since it cannot be linked by `-l`.
+    /// Static archives do not have NEEDED entries,
+    /// so `CEXTRALIB` and `FEXTRALIB` of `make_conf` used to build it are returned instead.
+    pub fn needed_as_link_flags(&self, make_conf: &MakeConf) -> LinkFlags {
+        if self.is_static {
+            return make_conf.c_extra_libs.merge(&make_conf.f_extra_libs);
+        }
+        let mut libs: Vec<String> = self
+            .libs
+            .iter()
+            .map(|lib| lib_name(lib))
+            .filter(|name| !name.starts_with("ld-"))
+            .map(String::from)
+            .collect();
+        libs.sort();
+        libs.dedup();
+        LinkFlags {
+            libs,
+            ..LinkFlags::default()
+        }
+    }
+
+    /// Linked libraries which are not found in any of `search_paths`
+    ///
+    /// Absolute paths, e.g. in `otool -L` output, are checked as is.
+    /// Default loader paths like `/usr/lib` are not searched unless included in `search_paths`.
+    pub fn unresolved_needed(&self, search_paths: &[PathBuf]) -> Vec<String> {
+        self.libs
+            .iter()
+            .filter(|lib| {
+                let lib = Path::new(lib);
+                if lib.is_absolute() {
+                    !lib.exists()
+                } else {
+                    !search_paths.iter().any(|dir| dir.join(lib).exists())
+                }
+            })
+            .cloned()
+            .collect()
+    }
+}
+
+/// Library name used in `-l` flag from its filename
+///
+/// - `libm.so.6` -> `m`
+/// - `libgfortran.5.dylib` -> `gfortran`
+/// - `libSystem.B.dylib` -> `System`
+/// - `ld-linux-x86-64.so.2` -> `ld-linux-x86-64`
+/// - `libgfortran-5.dll` -> `gfortran`
+fn lib_name(file: &str) -> &str {
+    let name = file.strip_prefix("lib").unwrap_or(file);
+    // Extensions of DLL are case-insensitive, e.g. `KERNEL32.DLL`
+    let lower = name.to_ascii_lowercase();
+    let mut name = [".so", ".dylib", ".dll"]
+        .iter()
+        .flat_map(|ext| lower.find(ext))
+        .min()
+        .map_or(name, |pos| &name[..pos]);
+    // Versions of dylib are not always numeric, e.g. `.B` of `libSystem.B.dylib`
+    if lower.contains(".dylib") {
+        return name.split('.').next().unwrap_or(name);
+    }
+    // Strip version before extension, e.g. `.5` of `libgfortran.so.5`
+    while let Some((stem, version)) = name.rsplit_once('.') {
+        if version.is_empty() || !version.chars().all(|c| c.is_ascii_digit()) {
+            break;
+        }
+        name = stem;
+    }
+    // MinGW puts version by `-` instead, e.g. `-5` of `libgfortran-5.dll`
+    if is_dll(file) {
+        if let Some((stem, version)) = name.rsplit_once('-') {
+            if !version.is_empty() && version.chars().all(|c| c.is_ascii_digit()) {
+                return stem;
+            }
+        }
+    }
+    name
+}
+
+/// Windows DLL or its import library, e.g. `libgfortran-5.dll`, `MSVCRT.dll` or `libopenblas.dll.a`
+fn is_dll(file: &str) -> bool {
+    file.to_ascii_lowercase().contains(".dll")
+}
+
+/// Directories to search system OpenBLAS, in priority order
+///
+/// Homebrew installs into `/opt/homebrew` on Apple silicon, and `/usr/local` on Intel Mac.
+fn system_lib_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    if cfg!(target_os = "macos") {
+        let brew = if cfg!(target_arch = "aarch64") {
+            "/opt/homebrew"
+        } else {
+            "/usr/local"
+        };
+        dirs.push(PathBuf::from(brew).join("opt/openblas/lib"));
+    }
+    for dir in [
+        "/opt/OpenBLAS/lib",
+        "/usr/local/lib",
+        "/usr/lib64",
+        "/usr/lib",
+    ] {
+        dirs.push(PathBuf::from(dir));
+    }
+    dirs
+}
+
+/// `libopenblas.{so,dylib,a,dll}` existing in `dir`
+fn openblas_files_in(dir: &Path) -> Vec<PathBuf> {
+    ["so", "dylib", "a", "dll"]
+        .iter()
+        .map(|ext| dir.join(format!("libopenblas.{}", ext)))
+        .filter(|path| path.is_file())
+        .collect()
+}
+
+/// Find the first `libopenblas.{so,dylib,a,dll}` in `dirs`
+fn find_openblas_in(dirs: impl IntoIterator<Item = PathBuf>) -> Option<PathBuf> {
+    dirs.into_iter()
+        .find_map(|dir| openblas_files_in(&dir).into_iter().next())
+}
+
+/// Find every `libopenblas.{so,dylib,a,dll}` in `search_paths`, e.g. [LinkFlags::search_paths]
+///
+/// More than one result means the linker may pick an unexpected copy.
+/// Directories and libraries are canonicalized, and the same file is reported only once.
+pub fn find_all_openblas(search_paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut found = Vec::new();
+    for dir in search_paths {
+        for path in openblas_files_in(dir) {
+            let path = path.canonicalize().unwrap_or(path);
+            push_unique(&mut found, path);
+        }
+    }
+    found
+}
+
+/// Match `name` with a glob `pattern`, where `*` matches any string including `/` and `?` any character
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let (pattern, name): (Vec<char>, Vec<char>) =
+        (pattern.chars().collect(), name.chars().collect());
+    // Position to retry when the last `*` should match one more character
+    let (mut p, mut n, mut retry) = (0, 0, None);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                retry = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match retry {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    retry = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Inspect a library in a tarball, e.g. build output cached by CI, without extracting it explicitly
+///
+/// The member matching `member_glob`, e.g. `*/lib/libopenblas.a`, is extracted by `tar` command
+/// into a temporary directory, which is removed before returning.
+/// [LibInspect::path] points to the extracted file, and does not exist anymore.
+/// Compressed tarballs are supported as far as `tar` detects the compression.
+pub fn inspect_in_archive<P: AsRef<Path>>(
+    tar_path: P,
+    member_glob: &str,
+) -> Result<LibInspect, Error> {
+    let tar_path = tar_path.as_ref();
+    let run_tar = |args: &[&OsStr]| -> Result<Vec<u8>, Error> {
+        Ok(spawn_tool(Command::new("tar").args(args))?
+            .wait(DEFAULT_BINUTIL_TIMEOUT)?
+            .stdout)
+    };
+
+    let listing = run_tar(&["-tf".as_ref(), tar_path.as_os_str()])?;
+    let matches: Vec<String> = String::from_utf8_lossy(&listing)
+        .lines()
+        .filter(|member| !member.ends_with('/'))
+        .filter(|member| glob_match(member_glob, member.trim_start_matches("./")))
+        .map(String::from)
+        .collect();
+    let member = match matches.as_slice() {
+        [member] => member,
+        [] => {
+            return Err(Error::LibraryNotExist {
+                path: tar_path.join(member_glob),
+            })
+        }
+        _ => {
+            return Err(Error::AmbiguousArchiveMember {
+                archive: tar_path.to_owned(),
+                matches,
+            })
+        }
+    };
+
+    let content = run_tar(&["-xOf".as_ref(), tar_path.as_os_str(), member.as_ref()])?;
+    let archive_name = tar_path.file_name().unwrap_or_default().to_string_lossy();
+    let dir = TempDir(env::temp_dir().join(format!(
+        "openblas-build-{}-{}",
+        std::process::id(),
+        TEMP_DIR_COUNT.fetch_add(1, Ordering::Relaxed)
+    )));
+    let member_dir = dir.0.join(archive_name.as_ref());
+    fs::create_dir_all(&member_dir)?;
+    let path = member_dir.join(Path::new(member).file_name().unwrap_or_default());
+    fs::write(&path, content)?;
+    LibInspect::new(path)
+}
+
+/// Number of temporary directories and files created in this process, to make their names unique
+static TEMP_DIR_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// 64-bit FNV-1a hash, which does not change between Rust versions unlike `DefaultHasher`
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// Directory removed on drop, even if an error occurs
+struct TempDir(PathBuf);
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Check the library contains kernels for `target`, e.g. it is not fallen back to another target
+///
+/// Kernels are detected by defined symbols named `*_kernel_<target>`, `*_direct_<target>`,
+/// or `gotoblas_<TARGET>`, e.g. `sgemm_direct_skylakex` for [crate::Target::SKYLAKEX].
+/// Generic targets, e.g. [crate::Target::SSE_GENERIC], and DYNAMIC_ARCH builds always pass.
+/// This also passes if no kernel is specific to any target, which is common for non-DYNAMIC_ARCH builds,
+/// so `false` means kernels only for other targets are found.
+pub fn verify_target(inspect: &LibInspect, target: &crate::Target) -> bool {
+    if target.as_str().contains("GENERIC") || !inspect.dynamic_arch_targets().is_empty() {
+        return true;
+    }
+    let mut found = inspect
+        .typed_symbols
+        .iter()
+        .filter(|(_, ty)| *ty != 'U')
+        .filter_map(|(sym, _)| {
+            sym.strip_prefix("gotoblas_")
+                .or_else(|| sym.split_once("_kernel_").map(|(_, tag)| tag))
+                .or_else(|| sym.split_once("_direct_").map(|(_, tag)| tag))
+        })
+        .filter_map(|tag| tag.parse::<crate::Target>().ok())
+        .filter(|found| !found.as_str().contains("GENERIC"))
+        .peekable();
+    found.peek().is_none() || found.any(|found| found == *target)
+}
+
+/// Check `SYMBOLSUFFIX` of [MakeConf] agrees with the symbol suffix of the library, e.g. `dgemm_64_`
+///
+/// OpenBLAS adds the suffix only if `SYMBOLSUFFIX` is set, not for every `INTERFACE64=1` build,
+/// so an ILP64 library without suffix is valid and cannot be told from a LP64 one by its symbols.
+/// Bindings generated for the other integer width silently corrupt arguments, so this should fail fast.
+pub fn validate_interface64(make_conf: &MakeConf, inspect: &LibInspect) -> Result<(), Error> {
+    let symbol_suffix = inspect.symbol_suffix();
+    if make_conf.symbol_suffix == symbol_suffix {
+        Ok(())
+    } else {
+        Err(Error::InterfaceMismatch {
+            expected_suffix: make_conf.symbol_suffix.clone(),
+            symbol_suffix,
+        })
+    }
+}
+
+/// Libraries whose position in a static link line is known, dependents first
+const STATIC_LINK_RANKS: &[&str] = &["gfortran", "quadmath", "gomp", "pthread", "dl", "m", "c"];
+
+/// Library names for static linking in an order GNU ld accepts, e.g. `openblas gfortran quadmath m`
+///
+/// `openblas` comes first, followed by `FEXTRALIB` of [MakeConf] without duplicates.
+/// Libraries of unknown dependency order, e.g. `flang`, keep their relative order just after `openblas`.
+pub fn static_link_order(make_conf: &MakeConf) -> Vec<String> {
+    let mut libs = vec!["openblas".to_string()];
+    for lib in &make_conf.f_extra_libs.libs {
+        if !libs.contains(lib) {
+            libs.push(lib.clone());
+        }
+    }
+    let rank = |lib: &String| {
+        STATIC_LINK_RANKS
+            .iter()
+            .position(|known| known == lib)
+            .map_or(0, |i| i + 1)
+    };
+    libs[1..].sort_by_key(rank);
+    libs
+}
+
+/// Find pre-installed OpenBLAS library, e.g. `/usr/lib/libopenblas.so`
+///
+/// Only the directory specified by `OPENBLAS_LIB_DIR` environment variable is searched if set.
+/// The result can be inspected by [LibInspect::new].
+pub fn find_system_openblas() -> Option<PathBuf> {
+    match env::var_os("OPENBLAS_LIB_DIR") {
+        Some(dir) => find_openblas_in([PathBuf::from(dir)]),
+        None => find_openblas_in(system_lib_dirs()),
+    }
+}
+
+/// Capabilities of the built library, summarized from [LibInspect]
+///
+/// This can be serialized with "serde" feature, e.g. to dump it into `OUT_DIR` for debugging.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Capabilities {
+    pub has_cblas: bool,
+    pub has_lapack: bool,
+    pub has_lapacke: bool,
+    pub threading: ThreadingModel,
+    /// Detected from ILP64 symbol suffix, e.g. `dgemm_64_`
+    pub interface64: bool,
+    pub version: Option<String>,
+}
+
+impl Capabilities {
+    /// Inspect library file, see [LibInspect::new]
+    pub fn inspect<P: AsRef<Path>>(path: P) -> Result<Self, Error> {
+        Ok(Self::from(&LibInspect::new(path)?))
+    }
+}
+
+impl From<&LibInspect> for Capabilities {
+    fn from(lib: &LibInspect) -> Self {
+        Capabilities {
+            has_cblas: lib.has_cblas(),
+            has_lapack: lib.has_lapack(),
+            has_lapacke: lib.has_lapacke(),
+            threading: lib.threading(),
+            interface64: lib.symbol_suffix().is_some(),
+            version: lib.version(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    /// Create an executable shell script under `test_build/fake_tools`
+    fn fake_tool(name: &str, script: &str) -> PathBuf {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("test_build/fake_tools")
+            .join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn find_openblas_in_dirs() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_build/find_openblas");
+        let (empty, dylib, both) = (root.join("empty"), root.join("dylib"), root.join("both"));
+        for dir in [&empty, &dylib, &both] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::write(dylib.join("libopenblas.dylib"), "").unwrap();
+        fs::write(both.join("libopenblas.a"), "").unwrap();
+        fs::write(both.join("libopenblas.so"), "").unwrap();
+
+        assert_eq!(find_openblas_in([empty.clone()]), None);
+        assert_eq!(
+            find_openblas_in([empty.clone(), dylib.clone(), both.clone()]),
+            Some(dylib.join("libopenblas.dylib"))
+        );
+        assert_eq!(
+            find_openblas_in([empty, both.clone(), dylib]),
+            Some(both.join("libopenblas.so"))
+        );
+    }
+
+    #[test]
+    fn link_flags_relative_to() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let base = root.join("src");
+        let line = "-L../ -L../src/ -L../not_exist -Wl,-rpath,.. -lm";
+        let info = LinkFlags::parse_relative_to(line, Some(&base)).unwrap();
+        let root = root.canonicalize().unwrap();
+        assert_eq!(info.search_paths, vec![root.clone(), root.join("src")]);
+        assert_eq!(info.rpaths, vec![root]);
+        assert_eq!(info.libs, vec!["m"]);
+    }
+
+    #[test]
+    fn link_flags_raw() {
+        let line = "-L/not_exist/lib -L/usr/../usr/lib -Wl,-rpath,/not_exist/lib -lm";
+        let info = LinkFlags::parse_raw(line).unwrap();
+        assert_eq!(
+            info.search_paths,
+            vec![
+                PathBuf::from("/not_exist/lib"),
+                PathBuf::from("/usr/../usr/lib")
+            ]
+        );
+        assert_eq!(info.rpaths, vec![PathBuf::from("/not_exist/lib")]);
+        assert_eq!(info.libs, vec!["m"]);
+        assert!(LinkFlags::parse(line).unwrap().rpaths.is_empty());
+
+        // Relative paths, response files, and jammed flags are kept as written
+        let info = LinkFlags::parse_raw("-Lnot_exist/lib -Lsrc-lm @Makefile.conf").unwrap();
+        assert_eq!(
+            info.search_paths,
+            vec![PathBuf::from("not_exist/lib"), PathBuf::from("src-lm")]
+        );
+        assert!(info.libs.is_empty());
+    }
+
+    #[test]
+    fn link_flags_with_search_path() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let mut info = LinkFlags::parse(&format!("-L{} -lm", root.join("src").display())).unwrap();
+        info.with_search_path(root.clone()).unwrap();
+        info.with_search_path(root.join("src/../src")).unwrap();
+        info.with_search_path(root.join("not_exist")).unwrap();
+        let root = root.canonicalize().unwrap();
+        assert_eq!(info.search_paths, vec![root.clone(), root.join("src")]);
+    }
+
+    #[test]
+    fn link_flags_quoted() {
+        assert_eq!(
+            split_flags(r#"-L"C:\Program Files\OpenBLAS\lib" -lopenblas"#),
+            vec![r"-LC:\Program Files\OpenBLAS\lib", "-lopenblas"]
+        );
+        assert_eq!(
+            split_flags("  -L'/opt/my libs'  -l\"m\" ''"),
+            vec!["-L/opt/my libs", "-lm", ""]
+        );
+
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let dir = root.join("test_build/Program Files/lib");
+        fs::create_dir_all(&dir).unwrap();
+        let info = LinkFlags::parse(&format!("-L\"{}\" -lm", dir.display())).unwrap();
+        assert_eq!(info.search_paths, vec![dir.canonicalize().unwrap()]);
+        assert_eq!(info.libs, vec!["m"]);
+
+        // Display quotes the path including whitespace to parse it back
+        let line = info.to_string();
+        assert!(line.starts_with("\"-L"));
+        assert_eq!(LinkFlags::parse(&line).unwrap(), info);
+        assert_eq!(quote_flag("-Lsay \"hi\"".into()), "'-Lsay \"hi\"'");
+    }
+
+    #[test]
+    fn link_flags_jammed() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let dir = root.join("test_build/jammed-libs");
+        fs::create_dir_all(&dir).unwrap();
+
+        let line = format!("-L{}-lm -L{} -lc", root.display(), dir.display());
+        let info = LinkFlags::parse(&line).unwrap();
+        assert_eq!(
+            info.search_paths,
+            vec![root.canonicalize().unwrap(), dir.canonicalize().unwrap()]
+        );
+        assert_eq!(info.libs, vec!["c", "m"]);
+
+        let line = format!("-L{}-l:libgfortran.a", root.display());
+        let info = LinkFlags::parse(&line).unwrap();
+        assert_eq!(info.search_paths, vec![root.canonicalize().unwrap()]);
+        assert_eq!(info.exact_libs, vec!["libgfortran.a"]);
+    }
+
+    #[test]
+    fn link_flags_response_file() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let dir = root.join("test_build/response_file");
+        fs::create_dir_all(&dir).unwrap();
+        let nested = dir.join("nested.rsp");
+        fs::write(
+            dir.join("libs.rsp"),
+            format!("-L/tmp -lm\n@{}\n", nested.display()),
+        )
+        .unwrap();
+        fs::write(&nested, "-lgfortran @libs.rsp @nested.rsp").unwrap();
+
+        let line = format!("@{} -lc @not_exist.rsp", dir.join("libs.rsp").display());
+        let info = LinkFlags::parse(&line).unwrap();
+        assert_eq!(
+            info.search_paths,
+            vec![PathBuf::from("/tmp").canonicalize().unwrap()]
+        );
+        assert_eq!(info.libs, vec!["c", "gfortran", "m"]);
+
+        let info = LinkFlags::parse_relative_to("@nested.rsp", Some(&dir)).unwrap();
+        assert_eq!(info.libs, vec!["gfortran", "m"]);
+    }
+
+    #[test]
+    fn link_flags_entries() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let root = root.canonicalize().unwrap();
+        let archive = root.join("libtiny.a");
+        let line = format!(
+            "-Wl,-rpath,{0} -lgfortran -L{0} -B{0} {1} -lm",
+            root.display(),
+            archive.display()
+        );
+        let entries: Vec<_> = LinkFlags::parse_ordered(&line).unwrap().entries().collect();
+        assert_eq!(
+            entries,
+            vec![
+                LinkEntry::SearchPath(root.clone()),
+                LinkEntry::BPrefix(root.clone()),
+                LinkEntry::Lib("gfortran".into()),
+                LinkEntry::Lib("m".into()),
+                LinkEntry::File(archive),
+                LinkEntry::Rpath(root),
+            ]
+        );
+        assert_eq!(LinkFlags::default().entries().count(), 0);
+    }
+
+    #[test]
+    fn link_flags_merge() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let c = LinkFlags::parse(&format!("-L{} -lm -lpthread", root.display())).unwrap();
+        let f = LinkFlags::parse(&format!("-L{} -lgfortran -lm", root.display())).unwrap();
+        let merged = c.merge(&f);
+        assert_eq!(merged.libs, vec!["gfortran", "m", "pthread"]);
+        assert_eq!(merged.search_paths, vec![root.canonicalize().unwrap()]);
+        assert_eq!(merged, f.merge(&c));
+    }
+
+    #[test]
+    fn find_all_openblas_copies() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_build/find_all_openblas");
+        let (system, local, empty) = (root.join("system"), root.join("local"), root.join("empty"));
+        for dir in [&system, &local, &empty] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        fs::write(system.join("libopenblas.so"), "").unwrap();
+        fs::write(local.join("libopenblas.a"), "").unwrap();
+
+        let system = system.canonicalize().unwrap();
+        let local = local.canonicalize().unwrap();
+        assert_eq!(
+            find_all_openblas(&[
+                system.clone(),
+                empty.clone(),
+                local.clone(),
+                system.join("../system")
+            ]),
+            vec![system.join("libopenblas.so"), local.join("libopenblas.a")]
+        );
+        assert!(find_all_openblas(&[empty]).is_empty());
+    }
+
+    #[test]
+    fn link_flags_exact_libs() {
+        let info = LinkFlags::parse("-lc -l:libgfortran.a").unwrap();
+        assert_eq!(info.libs, vec!["c"]);
+        assert_eq!(info.exact_libs, vec!["libgfortran.a"]);
+    }
+
+    #[test]
+    fn link_flags_whitespace() {
+        let info = LinkFlags::parse("-L/usr/lib\t-lc  -lm").unwrap();
+        assert_eq!(info.libs, vec!["c", "m"]);
+        assert!(info.search_paths.iter().all(|p| p != Path::new("")));
+    }
+
+    #[test]
+    fn link_flags_rpath() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let a = root.join("src");
+        let b = root.canonicalize().unwrap();
+
+        let info = LinkFlags::parse(&format!(
+            "-Wl,-rpath,{},{} -Wl,-rpath,/not/exist -lc",
+            a.display(),
+            b.display()
+        ))
+        .unwrap();
+        assert_eq!(info.rpaths, vec![b.clone(), a.canonicalize().unwrap()]);
+        assert_eq!(info.libs, vec!["c"]);
+
+        let info = LinkFlags::parse(&format!("-Wl,-rpath-link -Wl,{}", b.display())).unwrap();
+        assert_eq!(info.rpaths, vec![b]);
+    }
+
+    #[test]
+    fn link_flags_files() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let archive = root.join("libtiny.a").canonicalize().unwrap();
+        let info = LinkFlags::parse(&format!(
+            "{} -lm /not/exist/libfoo.a libbar.so",
+            archive.display()
+        ))
+        .unwrap();
+        assert_eq!(info.files, vec![archive.clone()]);
+        assert_eq!(info.libs, vec!["m"]);
+        assert!(info.exact_libs.is_empty());
+        assert_eq!(
+            &info.to_cargo_directives()[1..],
+            [
+                format!(
+                    "cargo:rustc-link-search=native={}",
+                    root.canonicalize().unwrap().display()
+                ),
+                "cargo:rustc-link-lib=static:+verbatim=libtiny.a".to_string(),
+            ]
+        );
+        assert_eq!(LinkFlags::parse(&info.to_string()).unwrap(), info);
+
+        let info = LinkFlags::parse(&format!("-l{}", archive.display())).unwrap();
+        assert_eq!(info.files, vec![archive]);
+        assert!(info.libs.is_empty());
+
+        let info = LinkFlags::parse("-l/not/exist/libfoo.a -lm").unwrap();
+        assert!(info.files.is_empty());
+        assert_eq!(info.libs, vec!["m"]);
+    }
+
+    #[test]
+    fn link_flags_b_prefix() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let a = root.join("src");
+        let b = root.canonicalize().unwrap();
+
+        let info = LinkFlags::parse(&format!(
+            "-B{} -B {} -B/usr/lib/gcc/not/exist -lc",
+            a.display(),
+            b.display()
+        ))
+        .unwrap();
+        assert_eq!(info.b_prefixes, vec![b.clone(), a.canonicalize().unwrap()]);
+        assert!(info.search_paths.is_empty());
+        assert_eq!(info.libs, vec!["c"]);
+        assert!(info
+            .to_cargo_directives()
+            .contains(&format!("cargo:rustc-link-search=native={}", b.display())));
+        assert_eq!(LinkFlags::parse(&info.to_string()).unwrap(), info);
+    }
+
+    #[test]
+    fn link_flags_cargo_directives() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .canonicalize()
+            .unwrap();
+
+        let info = LinkFlags::parse("-lm -lAccelerate.framework").unwrap();
+        assert_eq!(
+            info.to_cargo_directives(),
+            vec![
+                "cargo:rustc-link-lib=framework=Accelerate",
+                "cargo:rustc-link-lib=m",
+            ]
+        );
+
+        let info = LinkFlags::parse(&format!("-L{} -lc", root.display())).unwrap();
+        assert_eq!(
+            info.to_cargo_directives(),
+            vec![
+                format!("cargo:rustc-link-search=native={}", root.display()),
+                "cargo:rustc-link-lib=c".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn link_flags_framework() {
+        let info = LinkFlags::parse("-framework Accelerate -lSystem").unwrap();
+        assert_eq!(info.frameworks, vec!["Accelerate"]);
+        assert_eq!(info.libs, vec!["System"]);
+        assert_eq!(
+            info.to_cargo_directives(),
+            vec![
+                "cargo:rustc-link-lib=System",
+                "cargo:rustc-link-lib=framework=Accelerate",
+            ]
+        );
+    }
+
+    #[test]
+    fn link_flags_ordered() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let a = root.join("src").canonicalize().unwrap();
+        let b = root.canonicalize().unwrap();
+        let line = format!("-L{} -L{} -L{}", a.display(), b.display(), a.display());
+
+        let info = LinkFlags::parse_ordered(&line).unwrap();
+        assert_eq!(info.search_paths, vec![a.clone(), b.clone()]);
+
+        let info = LinkFlags::parse(&line).unwrap();
+        assert_eq!(info.search_paths, vec![b, a]);
+    }
+
+    /// Synthetic inspection result of a shared library with "T" symbols
+    fn inspect_symbols(symbols: &[&str]) -> LibInspect {
+        let mut symbols: Vec<String> = symbols.iter().map(|s| s.to_string()).collect();
+        symbols.sort();
+        LibInspect {
+            path: PathBuf::from("libopenblas.so"),
+            real_path: PathBuf::from("libopenblas.so"),
+            is_static: false,
+            libs: Vec::new(),
+            typed_symbols: symbols.iter().map(|s| (s.clone(), 'T')).collect(),
+            symbols,
+            size_bytes: 0,
+            runpaths: Vec::new(),
+            objdump: "objdump".into(),
+            timeout: DEFAULT_BINUTIL_TIMEOUT,
+        }
+    }
+
+    #[test]
+    fn has_symbol() {
+        let lib = inspect_symbols(&["openblas_set_num_threads", "LAPACKE_dgesdd", "dgemm_"]);
+        assert!(lib.has_symbol("LAPACKE_dgesdd"));
+        assert!(lib.has_symbol("openblas_set_num_threads"));
+        assert!(!lib.has_symbol("LAPACKE_dgesd"));
+        assert!(!lib.has_symbol("sgemm_"));
+
+        assert!(lib.has_symbol_prefix("LAPACKE_"));
+        assert!(lib.has_symbol_prefix("openblas_"));
+        assert!(!lib.has_symbol_prefix("cblas_"));
+        assert!(!lib.has_symbol_prefix("openblas_set_num_threads_"));
+    }
+
+    #[test]
+    fn has_cblas() {
+        assert!(inspect_symbols(&["cblas_dgemm", "cblas_xerbla"]).has_cblas());
+        assert!(inspect_symbols(&["cblas_ddot64_"]).has_cblas());
+        assert!(!inspect_symbols(&["cblas_xerbla", "dgemm_"]).has_cblas());
+        assert!(!inspect_symbols(&["dgemm_"]).has_cblas());
+    }
+
+    #[test]
+    fn has_blas() {
+        assert!(inspect_symbols(&["dgemm_", "sgemm_", "daxpy_"]).has_blas());
+        assert!(inspect_symbols(&["dgemm_64_", "sgemm_64_", "daxpy_64_"]).has_blas());
+        assert!(!inspect_symbols(&["cblas_dgemm", "cblas_sgemm", "sgemm_", "daxpy_"]).has_blas());
+    }
+
+    #[test]
+    fn probably_unstripped() {
+        let names: Vec<String> = (0..5000).map(|i| format!("sym{}_", i)).collect();
+        let names: Vec<&str> = names.iter().map(|s| s.as_str()).collect();
+        let lib = LibInspect {
+            size_bytes: 300 * 1024 * 1024,
+            ..inspect_symbols(&names)
+        };
+        assert!(lib.is_probably_unstripped());
+
+        // Same size but many symbols, e.g. DYNAMIC_ARCH build
+        let names: Vec<String> = (0..100_000).map(|i| format!("sym{}_", i)).collect();
+        let names: Vec<&str> = names.iter().map(|s| s.as_str()).collect();
+        let lib = LibInspect {
+            size_bytes: 300 * 1024 * 1024,
+            ..inspect_symbols(&names)
+        };
+        assert!(!lib.is_probably_unstripped());
+
+        let lib = LibInspect {
+            size_bytes: 30 * 1024 * 1024,
+            ..inspect_symbols(&["dgemm_"])
+        };
+        assert!(!lib.is_probably_unstripped());
+
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("libtiny.a");
+        let lib = LibInspect::new(&path).unwrap();
+        assert_eq!(lib.size_bytes, fs::metadata(&path).unwrap().len());
+        assert!(!lib.is_probably_unstripped());
+    }
+
+    #[test]
+    fn needed_as_link_flags() {
+        let lib = LibInspect {
+            libs: vec![
+                "ld-linux-x86-64.so.2".into(),
+                "libc.so.6".into(),
+                "libgfortran.so.5".into(),
+                "libm.so.6".into(),
+                "libpthread.so.0".into(),
+            ],
+            ..inspect_symbols(&[])
+        };
+        let make_conf = MakeConf {
+            f_extra_libs: LinkFlags::parse("-lgfortran -lquadmath").unwrap(),
+            ..MakeConf::default()
+        };
+        let flags = lib.needed_as_link_flags(&make_conf);
+        assert_eq!(flags.libs, vec!["c", "gfortran", "m", "pthread"]);
+        assert!(flags.search_paths.is_empty());
+        assert_eq!(flags.to_string(), "-lc -lgfortran -lm -lpthread");
+
+        let lib = LibInspect {
+            libs: vec!["libSystem.B.dylib".into(), "libgfortran.5.dylib".into()],
+            ..inspect_symbols(&[])
+        };
+        assert_eq!(
+            lib.needed_as_link_flags(&make_conf).libs,
+            vec!["System", "gfortran"]
+        );
+
+        // Static archives fall back to the extra libraries in Makefile.conf
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("libtiny.a");
+        let lib = LibInspect::new(path).unwrap();
+        assert_eq!(
+            lib.needed_as_link_flags(&make_conf).libs,
+            vec!["gfortran", "quadmath"]
+        );
+    }
+
+    #[test]
+    fn unresolved_needed() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_build/unresolved_needed");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("libgfortran.so.5"), "").unwrap();
+
+        let mut lib = inspect_symbols(&["dgemm_"]);
+        lib.libs = vec!["libgfortran.so.5".into(), "libquadmath.so.0".into()];
+        assert_eq!(
+            lib.unresolved_needed(std::slice::from_ref(&root)),
+            vec!["libquadmath.so.0"]
+        );
+        assert_eq!(
+            lib.unresolved_needed(&[]),
+            vec!["libgfortran.so.5", "libquadmath.so.0"]
+        );
+
+        lib.libs = vec![root.join("libgfortran.so.5").display().to_string()];
+        assert!(lib.unresolved_needed(&[]).is_empty());
+    }
+
+    #[test]
+    fn has_lapacke() {
+        assert!(inspect_symbols(&["LAPACKE_dsyev"]).has_lapacke());
+        assert!(inspect_symbols(&["lapacke_dsyev"]).has_lapacke());
+        assert!(inspect_symbols(&["lapacke_dsyev_work_"]).has_lapacke());
+        assert!(!inspect_symbols(&["dsyev_", "lapack"]).has_lapacke());
+    }
+
+    #[test]
+    fn looks_complete() {
+        let lib = inspect_symbols(&["dgemm_", "sgemm_", "daxpy_"]);
+        assert_eq!(lib.symbol_count(), 3);
+        assert!(!lib.looks_complete());
+
+        let names: Vec<String> = (0..MIN_SYMBOL_COUNT)
+            .map(|i| format!("sym{}_", i))
+            .collect();
+        let mut symbols: Vec<&str> = names.iter().map(|s| s.as_str()).collect();
+        assert!(!inspect_symbols(&symbols).looks_complete());
+
+        symbols.extend(["dgemm_", "sgemm_", "daxpy_"]);
+        let lib = inspect_symbols(&symbols);
+        assert_eq!(lib.symbol_count(), MIN_SYMBOL_COUNT + 3);
+        assert!(lib.looks_complete());
+    }
+
+    #[test]
+    fn diff() {
+        let mut old = inspect_symbols(&["cblas_dgemm", "dgemm_", "dsyev_", "xerbla_"]);
+        old.libs = vec!["libc.so.6".into(), "libgfortran.so.4".into()];
+        let mut new = inspect_symbols(&["cblas_dgemm", "dgemm_", "dgemmt_", "dsyev_"]);
+        new.libs = vec!["libc.so.6".into(), "libgfortran.so.5".into()];
+
+        let diff = old.diff(&new);
+        assert_eq!(diff.added, vec!["dgemmt_"]);
+        assert_eq!(diff.removed, vec!["xerbla_"]);
+        assert_eq!(diff.added_libs, vec!["libgfortran.so.5"]);
+        assert_eq!(diff.removed_libs, vec!["libgfortran.so.4"]);
+        assert!(!diff.is_empty());
+
+        assert_eq!(new.diff(&old).added, vec!["xerbla_"]);
+        assert!(old.diff(&old).is_empty());
+    }
+
+    #[test]
+    fn dynamic_arch_targets() {
+        let mut lib = inspect_symbols(&[
+            "dgemm_",
+            "dgemm_kernel_HASWELL",
+            "sgemm_kernel_HASWELL",
+            "dgemm_kernel_SKYLAKEX",
+            "daxpy_k_ZEN",
+            "dgemm_kernel_SSE_GENERIC",
+            "dgemm_kernel_haswell",
+            "LAPACKE_dgesv",
+        ]);
+        lib.typed_symbols.push(("gotoblas_NEHALEM".into(), 'D'));
+        lib.typed_symbols.push(("gotoblas_ATOM".into(), 'U'));
+        assert_eq!(
+            lib.dynamic_arch_targets(),
+            vec!["HASWELL", "NEHALEM", "SKYLAKEX", "SSE_GENERIC", "ZEN"]
+        );
+        assert!(inspect_symbols(&["dgemm_", "cblas_dgemm", "HASWELL"])
+            .dynamic_arch_targets()
+            .is_empty());
+    }
+
+    #[test]
+    fn lapack_completeness() {
+        let cases = [
+            (vec!["dsyev_", "LAPACKE_dsyev"], LapackCompleteness::Full),
+            (vec!["LAPACKE_dsyev"], LapackCompleteness::LapackeOnly),
+            (vec!["dsyev_"], LapackCompleteness::FortranOnly),
+            (vec!["dgemm_"], LapackCompleteness::None),
+        ];
+        for (symbols, expected) in cases {
+            assert_eq!(inspect_symbols(&symbols).lapack_completeness(), expected);
+        }
+    }
+
+    #[test]
+    fn lapack_provider() {
+        let mut lib = inspect_symbols(&["dgemm_", "dsyev_"]);
+        lib.libs = vec!["libc.so.6".into(), "libgfortran.so.5".into()];
+        assert_eq!(lib.lapack_provider(), LapackProvider::OpenBlasBuiltin);
+
+        let mut lib = inspect_symbols(&["dgemm_", "cblas_dgemm"]);
+        lib.libs = vec!["libc.so.6".into(), "liblapack.so.3".into()];
+        assert_eq!(
+            lib.lapack_provider(),
+            LapackProvider::ExternalNeeded("liblapack.so.3".into())
+        );
+
+        lib.libs = vec!["libc.so.6".into(), "liblapacke.so.3".into()];
+        assert_eq!(lib.lapack_provider(), LapackProvider::None);
+    }
+
+    #[test]
+    fn openmp_runtime() {
+        let mut lib = inspect_symbols(&["dgemm_"]);
+        lib.libs = vec!["libc.so.6".into(), "libpthread.so.0".into()];
+        assert_eq!(lib.openmp_runtime(), None);
+
+        for (file, runtime) in [
+            ("libgomp.so.1", "libgomp"),
+            ("libomp.so.5", "libomp"),
+            ("libiomp5.so", "libiomp5"),
+            ("libomp.dylib", "libomp"),
+        ] {
+            lib.libs = vec!["libc.so.6".into(), file.into()];
+            assert_eq!(lib.openmp_runtime().as_deref(), Some(runtime));
+            assert_eq!(lib.threading(), ThreadingModel::OpenMP);
+        }
+    }
+
+    #[test]
+    fn threading() {
+        let mut lib = inspect_symbols(&["dgemm_"]);
+        assert_eq!(lib.threading(), ThreadingModel::Sequential);
+
+        lib.typed_symbols.push(("pthread_create".into(), 'U'));
+        assert_eq!(lib.threading(), ThreadingModel::Pthread);
+
+        lib.typed_symbols.push(("GOMP_parallel".into(), 'U'));
+        assert_eq!(lib.threading(), ThreadingModel::OpenMP);
+
+        let mut lib = inspect_symbols(&["dgemm_"]);
+        lib.libs = vec!["libc.so.6".into(), "libpthread.so.0".into()];
+        assert_eq!(lib.threading(), ThreadingModel::Pthread);
+        lib.libs.push("libgomp.so.1".into());
+        assert_eq!(lib.threading(), ThreadingModel::OpenMP);
+    }
+
+    #[test]
+    fn verify_target() {
+        use crate::Target;
+        let lib = inspect_symbols(&["dgemm_", "sgemm_direct_skylakex"]);
+        assert!(super::verify_target(&lib, &Target::SKYLAKEX));
+        assert!(!super::verify_target(&lib, &Target::HASWELL));
+        assert!(super::verify_target(&lib, &Target::SSE_GENERIC));
+
+        // Non-DYNAMIC_ARCH builds, e.g. `TARGET=HASWELL`, have no target-specific symbols
+        let plain = inspect_symbols(&["dgemm_", "dgemm_kernel", "dgemm_beta", "sgemm_nn"]);
+        assert!(super::verify_target(&plain, &Target::HASWELL));
+        assert!(super::verify_target(&plain, &Target::SKYLAKEX));
+
+        // Names which merely end with a target name are not kernels
+        let other = inspect_symbols(&["dgemm_", "sgemm_direct_skylakex", "cpuid_atom", "blas_zen"]);
+        assert!(!super::verify_target(&other, &Target::ATOM));
+        assert!(!super::verify_target(&other, &Target::ZEN));
+
+        let dynamic = inspect_symbols(&["dgemm_", "dgemm_kernel_HASWELL", "dgemm_kernel_ZEN"]);
+        assert!(super::verify_target(&dynamic, &Target::SKYLAKEX));
+    }
+
+    #[test]
+    fn interface64_mismatch() {
+        let lp64 = MakeConf::default();
+        let ilp64 = MakeConf {
+            interface64: true,
+            ..MakeConf::default()
+        };
+        let ilp64_suffixed = MakeConf {
+            interface64: true,
+            symbol_suffix: Some("64_".into()),
+            ..MakeConf::default()
+        };
+        let plain = inspect_symbols(LP64_ARCHIVE_SYMBOLS);
+        let suffixed = inspect_symbols(&["daxpy_64_", "dgemm_64_", "dsyev_64_", "sgemm_64_"]);
+        assert!(validate_interface64(&lp64, &plain).is_ok());
+        // `INTERFACE64=1` without `SYMBOLSUFFIX` keeps the plain names
+        assert!(validate_interface64(&ilp64, &plain).is_ok());
+        assert!(validate_interface64(&ilp64_suffixed, &suffixed).is_ok());
+        match validate_interface64(&ilp64_suffixed, &plain) {
+            Err(Error::InterfaceMismatch {
+                expected_suffix: Some(expected),
+                symbol_suffix: None,
+            }) => assert_eq!(expected, "64_"),
+            other => panic!("Unexpected: {:?}", other),
+        }
+        match validate_interface64(&ilp64, &suffixed) {
+            Err(Error::InterfaceMismatch {
+                expected_suffix: None,
+                symbol_suffix: Some(suffix),
+            }) => assert_eq!(suffix, "64_"),
+            other => panic!("Unexpected: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn symbol_suffix() {
+        let lib = inspect_symbols(&["cblas_dgemm", "dgemm_", "dsyev_"]);
+        assert_eq!(lib.symbol_suffix(), None);
+        assert!(lib.has_cblas());
+        assert!(lib.has_lapack());
+
+        let lib = inspect_symbols(&[
+            "cblas_dgemm64_",
+            "daxpy_64_",
+            "dgemm_64_",
+            "dsyev_64_",
+            "sgemm_64_",
+        ]);
+        assert_eq!(lib.symbol_suffix().as_deref(), Some("64_"));
+        assert!(lib.has_cblas());
+        assert!(lib.has_lapack());
+
+        let lib = inspect_symbols(&["cblas_dgemm64_", "dgemm_64_", "dsyev_32_"]);
+        assert!(!lib.has_lapack());
+
+        // Internal kernels and extensions of LP64 libraries are not suffixes
+        let lib = inspect_symbols(&[
+            "cblas_dgemm",
+            "cblas_dgemmt",
+            "daxpy_",
+            "dgemm_",
+            "dgemm_beta",
+            "dgemm_kernel",
+            "dgemm_nn",
+            "sgemm_",
+            "sgemm_beta",
+        ]);
+        assert_eq!(lib.symbol_suffix(), None);
+        assert!(lib.has_blas());
+
+        // CBLAS wrappers only
+        let lib = inspect_symbols(&["cblas_daxpy64_", "cblas_dgemm64_", "cblas_sgemm64_"]);
+        assert_eq!(lib.symbol_suffix().as_deref(), Some("64_"));
+    }
+
+    #[test]
+    fn inspect_static_archive() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("libtiny.a");
+        let lib = LibInspect::new(path).unwrap();
+        assert!(lib.is_static());
+        assert!(lib.libs.is_empty());
+        assert!(lib.has_lapack());
+    }
+
+    #[test]
+    fn inspect_symlink() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let dir = root.join("test_build/inspect_symlink");
+        fs::create_dir_all(&dir).unwrap();
+        let real = dir.join("libopenblas.a.0.3.21");
+        fs::copy(root.join("libtiny.a"), &real).unwrap();
+        for (link, target) in [
+            ("libopenblas.a.0", "libopenblas.a.0.3.21"),
+            ("libopenblas.a", "libopenblas.a.0"),
+        ] {
+            let link = dir.join(link);
+            if link.symlink_metadata().is_err() {
+                std::os::unix::fs::symlink(target, &link).unwrap();
+            }
+        }
+
+        let lib = LibInspect::new(dir.join("libopenblas.a")).unwrap();
+        assert_eq!(lib.path(), dir.join("libopenblas.a"));
+        assert_eq!(lib.real_path(), real.canonicalize().unwrap());
+        assert!(lib.has_lapack());
+    }
+
+    #[test]
+    fn glob() {
+        assert!(glob_match(
+            "*/lib/libopenblas.a",
+            "prefix/lib/libopenblas.a"
+        ));
+        assert!(glob_match("*libopenblas.so.?", "lib/libopenblas.so.0"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("*/libopenblas.a", "libopenblas.a"));
+        assert!(!glob_match("lib/*.a", "lib/libopenblas.so"));
+    }
+
+    #[test]
+    fn inspect_in_tarball() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let dir = root.join("test_build/inspect_in_archive");
+        let lib_dir = dir.join("prefix/lib");
+        fs::create_dir_all(&lib_dir).unwrap();
+        fs::copy(root.join("libtiny.a"), lib_dir.join("libopenblas.a")).unwrap();
+        fs::copy(root.join("libtiny.a"), lib_dir.join("libopenblas.so")).unwrap();
+        let tar = dir.join("openblas.tar");
+        let status = Command::new("tar")
+            .arg("-cf")
+            .arg(&tar)
+            .arg("-C")
+            .arg(&dir)
+            .arg("prefix")
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let lib = inspect_in_archive(&tar, "*/lib/libopenblas.a").unwrap();
+        assert!(lib.is_static());
+        assert!(lib.has_lapack());
+        assert_eq!(lib.path().file_name().unwrap(), "libopenblas.a");
+        // Temporary directory is removed
+        assert!(!lib.path().exists());
+        assert!(!lib.path().parent().unwrap().parent().unwrap().exists());
+
+        match inspect_in_archive(&tar, "*/libopenblas.*") {
+            Err(Error::AmbiguousArchiveMember { matches, .. }) => assert_eq!(matches.len(), 2),
+            other => panic!("Unexpected: {:?}", other),
+        }
+        assert!(matches!(
+            inspect_in_archive(&tar, "*/libopenblas.dylib"),
+            Err(Error::LibraryNotExist { .. })
+        ));
+
+        // Not a tarball
+        match inspect_in_archive(root.join("libtiny.a"), "*") {
+            Err(Error::BinutilFailed { tool, .. }) => assert_eq!(tool, "tar"),
+            other => panic!("Unexpected: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn inspect_from_dir() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let dir = root.join("test_build/inspect_from_dir");
+        let (static_only, shared) = (dir.join("static_only"), dir.join("shared"));
+        for d in [&dir, &static_only, &shared] {
+            fs::create_dir_all(d).unwrap();
+        }
+
+        // Empty files cannot be inspected, or do not have any symbols
+        fs::copy(root.join("libtiny.a"), static_only.join("libopenblas.a")).unwrap();
+        fs::write(static_only.join("libopenblas.so"), "").unwrap();
+        let lib = LibInspect::from_dir(&static_only).unwrap();
+        assert_eq!(lib.path(), static_only.join("libopenblas.a"));
+
+        fs::write(shared.join("libopenblas.a"), "").unwrap();
+        fs::copy(root.join("libtiny.a"), shared.join("libopenblas.so.0")).unwrap();
+        let lib = LibInspect::from_dir(&shared).unwrap();
+        assert_eq!(lib.path(), shared.join("libopenblas.so.0"));
+        assert!(lib.has_lapack());
+
+        let empty = dir.join("empty");
+        fs::create_dir_all(&empty).unwrap();
+        assert!(matches!(
+            LibInspect::from_dir(&empty),
+            Err(Error::LibraryNotExist { .. })
+        ));
+    }
+
+    #[test]
+    fn inspect_with_tools() {
+        let nm = fake_tool("nm_lapacke", "echo '0000000000000000 T LAPACKE_dgesv'");
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("libtiny.a");
+        let lib = LibInspect::with_tools(path, nm.to_str().unwrap(), "objdump").unwrap();
+        assert_eq!(lib.symbols, vec!["LAPACKE_dgesv"]);
+        assert!(lib.has_lapacke());
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn inspect_filtered() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("libtiny.a");
+        let full = LibInspect::new(&path).unwrap();
+        let lib = LibInspect::new_filtered(&path, &["dsyev_"]).unwrap();
+        assert!(lib.symbols.len() < full.symbols.len());
+        assert!(lib.symbols.iter().all(|sym| sym.starts_with("dsyev_")));
+        assert!(lib
+            .typed_symbols
+            .iter()
+            .all(|(sym, _)| sym.starts_with("dsyev_")));
+        assert!(lib.has_lapack());
+
+        let lib = LibInspect::new_filtered(&path, &[]).unwrap();
+        assert!(lib.typed_symbols.is_empty());
+    }
+
+    #[test]
+    fn inspect_mach_o() {
+        let nm = fake_tool(
+            "nm_mach_o",
+            "echo '0000000000001000 T _dsyev_'; echo '0000000000002000 T _cblas_dgemm'; echo '                 U _gfortran_st_write'",
+        );
+        let objdump = fake_tool("objdump_empty", "true");
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_build/mach_o");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("libopenblas.dylib");
+        // MH_MAGIC_64 in little endian
+        fs::write(&path, [0xcf, 0xfa, 0xed, 0xfe, 0x07, 0x00, 0x00, 0x01]).unwrap();
+        let lib =
+            LibInspect::with_tools(&path, nm.to_str().unwrap(), objdump.to_str().unwrap()).unwrap();
+        assert_eq!(lib.symbols, vec!["cblas_dgemm", "dsyev_"]);
+        assert!(lib.has_lapack());
+        assert!(lib.has_cblas());
+        assert!(lib
+            .typed_symbols
+            .contains(&("gfortran_st_write".to_string(), 'U')));
+
+        // ELF symbols are kept as is
+        let elf = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("libtiny.a");
+        let lib =
+            LibInspect::with_tools(&elf, nm.to_str().unwrap(), objdump.to_str().unwrap()).unwrap();
+        assert!(!lib.has_lapack());
+    }
+
+    #[test]
+    fn inspect_timeout() {
+        let nm = fake_tool("nm_hung", "exec sleep 10");
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("libtiny.a");
+        let start = Instant::now();
+        let err = LibInspect::with_timeout(
+            path,
+            nm.to_str().unwrap(),
+            "objdump",
+            Duration::from_millis(200),
+        )
+        .unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        match &err {
+            Error::BinutilTimeout { tool } => assert_eq!(tool, nm.to_str().unwrap()),
+            _ => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn bitness_with_tools() {
+        let nm = fake_tool("nm_dgemm", "echo '0000000000000000 T dgemm_'");
+        let objdump = fake_tool(
+            "objdump_elf32",
+            "echo 'dgemm.o:     file format elf32-i386'",
+        );
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("libtiny.a");
+        let lib =
+            LibInspect::with_tools(&path, nm.to_str().unwrap(), objdump.to_str().unwrap()).unwrap();
+        assert_eq!(lib.bitness(), Some(Bitness::Bits32));
+
+        // Commands after inspection are killed by the same timeout
+        let objdump = fake_tool("objdump_hung", "exec sleep 10");
+        let lib = LibInspect::with_timeout(
+            &path,
+            nm.to_str().unwrap(),
+            objdump.to_str().unwrap(),
+            Duration::from_millis(200),
+        )
+        .unwrap();
+        let start = Instant::now();
+        assert_eq!(lib.bitness(), None);
+        assert!(start.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn inspect_nm_failed() {
+        let nm = fake_tool(
+            "nm_failed",
+            "echo 'nm: libtiny.a: file format not recognized' >&2; exit 1",
+        );
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("libtiny.a");
+        let err = LibInspect::with_tools(path, nm.to_str().unwrap(), "objdump").unwrap_err();
+        match &err {
+            Error::BinutilFailed {
+                tool,
+                status,
+                stderr,
+            } => {
+                assert_eq!(tool, nm.to_str().unwrap());
+                assert_eq!(status.code(), Some(1));
+                assert_eq!(stderr, "nm: libtiny.a: file format not recognized");
+            }
+            _ => panic!("Unexpected error: {:?}", err),
+        }
+    }
+
+    #[test]
+    fn inspect_without_objdump() {
+        let nm = fake_tool("nm_empty", "true");
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Makefile.conf");
+        // Neither `objdump` nor `readelf` exist in this directory
+        let objdump = "/not/installed/objdump";
+        let err = LibInspect::with_tools(path, nm.to_str().unwrap(), objdump).unwrap_err();
+        match &err {
+            Error::BinutilTool { tool, .. } => assert_eq!(tool, objdump),
+            _ => panic!("Unexpected error: {:?}", err),
+        }
+        assert!(err
+            .to_string()
+            .starts_with("Failed to run `/not/installed/objdump`"));
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn inspect_readelf_fallback() {
+        // Only `readelf` exists in this directory
+        let readelf = fake_tool(
+            "readelf_only/readelf",
+            r#"case "$1" in
+  -d) echo ' 0x0000000000000001 (NEEDED)             Shared library: [libgfortran.so.5]' ;;
+  *) echo '    12: 0000000000001139    11 FUNC    GLOBAL DEFAULT   14 dsyev_' ;;
+esac"#,
+        );
+        let dir = readelf.parent().unwrap();
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Makefile.conf");
+        let lib = LibInspect::with_tools(
+            path,
+            dir.join("nm").to_str().unwrap(),
+            dir.join("objdump").to_str().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(lib.libs, vec!["libgfortran.so.5"]);
+        assert_eq!(lib.symbols, vec!["dsyev_"]);
+    }
+
+    #[test]
+    fn readelf_output() {
+        let out = b"
+Symbol table '.dynsym' contains 4 entries:
+   Num:    Value          Size Type    Bind   Vis      Ndx Name
+     0: 0000000000000000     0 NOTYPE  LOCAL  DEFAULT  UND 
+     1: 0000000000000000     0 FUNC    GLOBAL DEFAULT  UND pthread_create@GLIBC_2.2.5 (2)
+     2: 0000000000001139    11 FUNC    GLOBAL DEFAULT   14 dsyev_
+     3: 0000000000001150    11 FUNC    WEAK   DEFAULT   14 LAPACKE_dsyev
+";
+        assert_eq!(
+            parse_readelf_syms(out),
+            vec![
+                ("LAPACKE_dsyev".to_string(), 'W'),
+                ("dsyev_".to_string(), 'T'),
+                ("pthread_create".to_string(), 'U'),
+            ]
+        );
+
+        let out = b"
+Dynamic section at offset 0x2de8 contains 26 entries:
+  Tag        Type                         Name/Value
+ 0x0000000000000001 (NEEDED)             Shared library: [libm.so.6]
+ 0x0000000000000001 (NEEDED)             Shared library: [libc.so.6]
+ 0x000000000000000e (SONAME)             Library soname: [libopenblas.so.0]
+";
+        assert_eq!(parse_readelf_needed(out), vec!["libm.so.6", "libc.so.6"]);
+        assert!(parse_readelf_runpaths(out).is_empty());
+
+        let out = b"
+ 0x0000000000000001 (NEEDED)             Shared library: [libm.so.6]
+ 0x000000000000001d (RUNPATH)            Library runpath: [/opt/OpenBLAS/lib:$ORIGIN]
+";
+        assert_eq!(
+            parse_readelf_runpaths(out),
+            vec![PathBuf::from("/opt/OpenBLAS/lib"), PathBuf::from("$ORIGIN")]
+        );
+        assert_eq!(
+            readelf_for("aarch64-linux-gnu-objdump"),
+            "aarch64-linux-gnu-readelf"
+        );
+    }
+
+    #[test]
+    fn nm_output() {
+        let out = b"
+libopenblas.a(dsyev.o):
+0000000000000000 T dsyev_
+0000000000000000 W LAPACKE_dsyev
+0000000000000010 R openblas_version
+                 U pthread_create
+";
+        let symbols = parse_nm(out);
+        assert_eq!(
+            symbols,
+            vec![
+                ("LAPACKE_dsyev".to_string(), 'W'),
+                ("dsyev_".to_string(), 'T'),
+                ("openblas_version".to_string(), 'R'),
+                ("pthread_create".to_string(), 'U'),
+            ]
+        );
+
+        let nm = fake_tool("nm_weak", "echo '0000000000000000 W LAPACKE_dsyev'");
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("libtiny.a");
+        let lib = LibInspect::with_tools(path, nm.to_str().unwrap(), "objdump").unwrap();
+        assert!(lib.symbols.is_empty());
+        assert!(lib.has_lapacke());
+    }
+
+    #[test]
+    fn objdump_bitness() {
+        let out = b"
+libopenblas.so:     file format elf64-x86-64
+architecture: i386:x86-64, flags 0x00000150:
+HAS_SYMS, DYNAMIC, D_PAGED
+start address 0x0000000000000000
+";
+        assert_eq!(parse_objdump_format(out), Some(Bitness::Bits64));
+
+        let out = b"
+In archive libopenblas.a:
+
+dgemm.o:     file format elf32-i386
+architecture: i386, flags 0x00000011:
+";
+        assert_eq!(parse_objdump_format(out), Some(Bitness::Bits32));
+        assert_eq!(
+            parse_objdump_format(b"libopenblas.dll:     file format pei-i386\n"),
+            Some(Bitness::Bits32)
+        );
+        assert_eq!(
+            parse_objdump_format(b"libopenblas.so:     file format elf32-x86-64\n"),
+            Some(Bitness::Bits32)
+        );
+        assert_eq!(
+            parse_objdump_format(b"libopenblas.dll:     file format pei-x86-64\n"),
+            Some(Bitness::Bits64)
+        );
+        assert_eq!(
+            parse_objdump_format(b"libopenblas.dylib:     file format mach-o-arm64\n"),
+            Some(Bitness::Bits64)
+        );
+        assert_eq!(
+            parse_objdump_format(b"objdump: file format not recognized\n"),
+            None
+        );
+    }
+
+    #[test]
+    fn dumpbin_import_dll() {
+        let out = b"
+Microsoft (R) COFF/PE Dumper Version 14.29.30133.0
+
+Dump of file libopenblas.lib
+
+File Type: LIBRARY
+
+  Version      : 0
+  Machine      : 8664 (x64)
+  TimeDateStamp: 6083A1B2 Sat Apr 24 13:31:14 2021
+  SizeOfData   : 0000001E
+  DLL name     : libopenblas.dll
+  Symbol name  : dgemm_
+  Type         : code
+  Name type    : name
+  Hint         : 0
+  Name         : dgemm_
+";
+        assert_eq!(
+            parse_dumpbin_import_dll(out).as_deref(),
+            Some("libopenblas.dll")
+        );
+
+        let out = b"
+Dump of file libopenblas.a
+
+File Type: LIBRARY
+
+FILE HEADER VALUES
+            8664 machine (x64)
+";
+        assert_eq!(parse_dumpbin_import_dll(out), None);
+    }
+
+    #[test]
+    fn strings_version() {
+        let out = b"GCC: (GNU) 10.2.0
+OpenBLAS is built with
+OpenBLAS 0.3.14 DYNAMIC_ARCH
+NO_AFFINITY
+";
+        assert_eq!(parse_version(out).as_deref(), Some("0.3.14"));
+        assert_eq!(parse_version(b"OpenBLAS\nNO_AFFINITY\n"), None);
+
+        let lib = inspect_symbols(&["dgemm_"]);
+        assert_eq!(lib.version(), None);
+    }
+
+    #[test]
+    fn inspect_from_outputs() {
+        let nm = b"
+libopenblas.a(dgemm.o):
+0000000000000000 T dgemm_
+0000000000000000 T \xff\xfe_broken
+                 U GOMP_parallel
+";
+        let objdump = b"
+Dynamic Section:
+  NEEDED               libgomp.so.1
+  NEEDED               libc.so.6
+  SONAME               libopenblas.so.0
+";
+        let lib = LibInspect::from_outputs(nm, objdump);
+        assert!(lib.has_symbol("dgemm_"));
+        assert_eq!(lib.symbols.len(), 2);
+        assert_eq!(lib.libs, vec!["libc.so.6", "libgomp.so.1"]);
+        assert_eq!(lib.threading(), ThreadingModel::OpenMP);
+        assert_eq!(lib.path(), Path::new(""));
+        assert!(lib.runpaths().is_empty());
+    }
+
+    #[test]
+    fn ldd_output() {
+        let out = b"
+	linux-vdso.so.1 (0x00007ffc8d5e2000)
+	libgfortran.so.5 => /lib/x86_64-linux-gnu/libgfortran.so.5 (0x00007f2d4c000000)
+	libm.so.6 => /lib/x86_64-linux-gnu/libm.so.6 (0x00007f2d4df19000)
+	libfoo.so.1 => not found
+	/lib64/ld-linux-x86-64.so.2 (0x00007f2d4e8b0000)
+";
+        assert_eq!(
+            parse_ldd(out),
+            vec![
+                (
+                    "libgfortran.so.5".to_string(),
+                    Some(PathBuf::from("/lib/x86_64-linux-gnu/libgfortran.so.5"))
+                ),
+                (
+                    "libm.so.6".to_string(),
+                    Some(PathBuf::from("/lib/x86_64-linux-gnu/libm.so.6"))
+                ),
+                ("libfoo.so.1".to_string(), None),
+            ]
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn resolved_deps_ldd_failed() {
+        // `ldd` fails for a static archive
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("libtiny.a");
+        let lib = LibInspect::new(path).unwrap();
+        assert!(matches!(
+            lib.resolved_deps(),
+            Err(Error::BinutilFailed { .. }) | Err(Error::BinutilTool { .. })
+        ));
+    }
+
+    #[test]
+    fn objdump_runpaths() {
+        let objdump = b"
+Dynamic Section:
+  NEEDED               libgfortran.so.5
+  RUNPATH              /opt/OpenBLAS/lib:$ORIGIN/../lib
+  SONAME               libopenblas.so.0
+";
+        assert_eq!(
+            parse_objdump_runpaths(objdump),
+            vec![
+                PathBuf::from("/opt/OpenBLAS/lib"),
+                PathBuf::from("$ORIGIN/../lib")
+            ]
+        );
+        let lib = LibInspect::from_outputs(b"", objdump);
+        assert_eq!(lib.runpaths().len(), 2);
+        assert!(lib.has_absolute_runpath());
+        assert!(!LibInspect::from_outputs(b"", b"  RPATH  $ORIGIN\n").has_absolute_runpath());
+    }
+
+    #[test]
+    fn dumpbin_output() {
+        let out = b"Microsoft (R) COFF/PE Dumper Version 14.28.29337.0
+
+Dump of file libopenblas.lib
+
+File Type: LIBRARY
+
+COFF SYMBOL TABLE
+000 01047A5F ABS    notype       Static       | @comp.id
+008 00000000 SECT3  notype ()    External     | dgemm_
+009 00000000 SECT3  notype ()    Static       | dgemm_kernel
+00A 00000000 UNDEF  notype ()    External     | pthread_create
+00B 00000010 SECT4  notype       External     | openblas_version
+";
+        assert_eq!(
+            parse_dumpbin_symbols(out),
+            vec![
+                ("dgemm_".to_string(), 'T'),
+                ("openblas_version".to_string(), 'D'),
+                ("pthread_create".to_string(), 'U'),
+            ]
+        );
+
+        let out = b"Dump of file libopenblas.dll
+
+File Type: DLL
+
+  Image has the following dependencies:
+
+    libgfortran-5.dll
+    KERNEL32.dll
+    msvcrt.dll
+
+  Summary
+
+        1000 .bss
+";
+        assert_eq!(
+            parse_dumpbin_dependents(out),
+            vec!["libgfortran-5.dll", "KERNEL32.dll", "msvcrt.dll"]
+        );
+    }
+
+    #[test]
+    fn stripped() {
+        let lib = LibInspect::from_outputs(b"", b"");
+        assert!(lib.is_stripped());
+        let lib = LibInspect::from_outputs(b"                 U pthread_create\n", b"");
+        assert!(lib.is_stripped());
+        let lib = LibInspect::from_outputs(b"0000000000000000 T dgemm_\n", b"");
+        assert!(!lib.is_stripped());
+    }
+
+    #[test]
+    fn has_lib() {
+        let lib = LibInspect::from_outputs(
+            b"",
+            b"  NEEDED libm.so.6\n  NEEDED libgfortran.so.5\n  NEEDED ld-linux-x86-64.so.2\n",
+        );
+        assert!(lib.has_lib("m"));
+        assert!(lib.has_lib("gfortran"));
+        assert!(lib.has_lib("ld-linux-x86-64"));
+        assert!(!lib.has_lib("quadmath"));
+
+        assert_eq!(lib_name("libgfortran.5.dylib"), "gfortran");
+        assert_eq!(lib_name("libquadmath.so.0.0.0"), "quadmath");
+        assert_eq!(lib_name("libopenblas.so"), "openblas");
+    }
+
+    #[test]
+    fn has_lib_dll() {
+        let lib = LibInspect {
+            libs: vec![
+                "KERNEL32.dll".into(),
+                "libgfortran-5.dll".into(),
+                "msvcrt.dll".into(),
+                "quadmath.DLL".into(),
+            ],
+            ..inspect_symbols(&[])
+        };
+        assert!(lib.has_lib("gfortran"));
+        assert!(lib.has_lib("kernel32"));
+        assert!(lib.has_lib("MSVCRT"));
+        assert!(lib.has_lib("quadmath"));
+        assert!(!lib.has_lib("gfortran-5"));
+        assert!(!lib.has_lib("m"));
+
+        assert_eq!(lib_name("libopenblas.dll.a"), "openblas");
+        assert_eq!(lib_name("libopenblas-0.3.dll"), "openblas");
+        assert_eq!(lib_name("libfoo-bar.so.1"), "foo-bar");
+    }
+
+    #[test]
+    fn capabilities() {
+        let lib = inspect_symbols(&[
+            "cblas_dgemm64_",
+            "daxpy_64_",
+            "dgemm_64_",
+            "dsyev_64_",
+            "sgemm_64_",
+        ]);
+        let cap = Capabilities::from(&lib);
+        assert!(cap.has_cblas);
+        assert!(cap.has_lapack);
+        assert!(!cap.has_lapacke);
+        assert!(cap.interface64);
+        assert_eq!(cap.threading, ThreadingModel::Sequential);
+    }
+
+    /// Exported symbols excerpted from a real LP64 `libopenblas.a` including internal kernels
+    const LP64_ARCHIVE_SYMBOLS: &[&str] = &[
+        "cblas_daxpy",
+        "cblas_dgemm",
+        "cblas_dgemmt",
+        "cblas_sgemm",
+        "daxpy_",
+        "daxpy_k",
+        "dgemm_",
+        "dgemm_beta",
+        "dgemm_incopy",
+        "dgemm_kernel",
+        "dgemm_nn",
+        "dgemm_tn",
+        "dsyev_",
+        "sgemm_",
+        "sgemm_beta",
+        "sgemm_kernel",
+        "sgemm_nn",
+    ];
+
+    #[test]
+    fn capabilities_lp64_archive() {
+        let cap = Capabilities::from(&inspect_symbols(LP64_ARCHIVE_SYMBOLS));
+        assert!(cap.has_cblas);
+        assert!(cap.has_lapack);
+        assert!(!cap.interface64);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn capabilities_json() {
+        let cap = Capabilities {
+            has_cblas: true,
+            has_lapack: true,
+            has_lapacke: false,
+            threading: ThreadingModel::OpenMP,
+            interface64: false,
+            version: Some("0.3.14".into()),
+        };
+        assert_eq!(
+            serde_json::to_string(&cap).unwrap(),
+            r#"{"has_cblas":true,"has_lapack":true,"has_lapacke":false,"threading":"OpenMP","interface64":false,"version":"0.3.14"}"#
+        );
+    }
+
+    #[test]
+    fn inspect_cached() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let work = root.join("test_build/inspect_cached");
+        let _ = fs::remove_dir_all(&work);
+        fs::create_dir_all(&work).unwrap();
+        let lib_path = work.join("libfake.a");
+        fs::copy(root.join("libtiny.a"), &lib_path).unwrap();
+
+        // Count how many times `nm` runs
+        let count = work.join("count");
+        let nm = fake_tool(
+            "nm_counting",
+            &format!(
+                "echo run >> {}\necho '0000000000000000 T dgemm_'",
+                count.display()
+            ),
+        );
+        let inspect = |path: &Path| LibInspect::with_tools(path, nm.to_str().unwrap(), "objdump");
+        let runs = || fs::read_to_string(&count).unwrap().lines().count();
+
+        let cache_dir = work.join("cache");
+        let lib = LibInspect::cached(&lib_path, &cache_dir, inspect).unwrap();
+        assert_eq!(runs(), 1);
+        let cached = LibInspect::cached(&lib_path, &cache_dir, inspect).unwrap();
+        assert_eq!(runs(), 1);
+        assert_eq!(cached.symbols, lib.symbols);
+        assert_eq!(cached.typed_symbols, lib.typed_symbols);
+        assert!(cached.is_static());
+
+        // Only the cache file is left, named by a stable hash
+        let files: Vec<_> = fs::read_dir(&cache_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        let cache = cache_dir.join(format!(
+            "{:016x}.txt",
+            fnv1a(lib_path.to_string_lossy().as_bytes())
+        ));
+        assert_eq!(files, vec![cache.clone()]);
+
+        // truncated cache file
+        let content = fs::read_to_string(&cache).unwrap();
+        let truncated = content.rsplit_once("end ").unwrap().0;
+        fs::write(&cache, truncated).unwrap();
+        LibInspect::cached(&lib_path, &cache_dir, inspect).unwrap();
+        assert_eq!(runs(), 2);
+        assert_eq!(fs::read_to_string(&cache).unwrap(), content);
+
+        // size changes
+        fs::write(&lib_path, b"!<arch>\n").unwrap();
+        LibInspect::cached(&lib_path, &cache_dir, inspect).unwrap();
+        assert_eq!(runs(), 3);
+    }
+
+    #[cfg(not(target_os = "macos"))]
+    #[test]
+    fn inspect_concurrently() {
+        let nm = fake_tool(
+            "nm_slow",
+            "sleep 0.2; echo '0000000000000000 T dgemm_'; echo '                 U GOMP_parallel'",
+        );
+        let objdump = fake_tool("objdump_slow", "sleep 0.2; echo '  NEEDED libgomp.so.1'");
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Makefile.conf");
+        let lib =
+            LibInspect::with_tools(path, nm.to_str().unwrap(), objdump.to_str().unwrap()).unwrap();
+        assert_eq!(lib.symbols, vec!["dgemm_"]);
+        assert_eq!(lib.libs, vec!["libgomp.so.1"]);
+        assert_eq!(lib.threading(), ThreadingModel::OpenMP);
+    }
+
+    #[test]
+    fn otool_output() {
+        let out = b"/usr/local/opt/openblas/lib/libopenblas.dylib:
+\t/usr/local/opt/openblas/lib/libopenblas.0.dylib (compatibility version 0.0.0, current version 0.0.0)
+\t/usr/local/opt/gcc/lib/gcc/10/libgfortran.5.dylib (compatibility version 6.0.0, current version 6.0.0)
+\t/usr/lib/libSystem.B.dylib (compatibility version 1.0.0, current version 1292.60.1)
+";
+        let libs = parse_otool(out);
+        assert_eq!(
+            libs,
+            vec![
+                "libopenblas.0.dylib",
+                "libgfortran.5.dylib",
+                "libSystem.B.dylib"
+            ]
+        );
+    }
+
+    #[test]
+    fn detail_from_makefile_conf() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Makefile.conf");
+        assert!(path.exists());
+        let detail = MakeConf::new(path).unwrap();
+        assert!(!detail.no_fortran);
+    }
+
+    #[test]
+    fn detail_from_nofortran_conf() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("nofortran.conf");
+        assert!(path.exists());
+        let detail = MakeConf::new(path).unwrap();
+        assert!(detail.no_fortran);
+    }
+
+    #[test]
+    fn detail_no_fortran_level() {
+        let dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("test_build/no_fortran_level");
+        fs::create_dir_all(&dir).unwrap();
+        for (value, no_fortran, level) in [
+            ("0", false, Some(0)),
+            ("1", true, Some(1)),
+            ("2", true, Some(2)),
+            ("", true, None),
+        ] {
+            let path = dir.join(format!("nofortran{}.conf", value));
+            fs::write(&path, format!("OSNAME=Linux\nNOFORTRAN={}\n", value)).unwrap();
+            let detail = MakeConf::new(&path).unwrap();
+            assert_eq!(detail.no_fortran, no_fortran, "NOFORTRAN={}", value);
+            assert_eq!(detail.no_fortran_level, level, "NOFORTRAN={}", value);
+        }
+    }
+
+    #[test]
+    fn detail_quoted() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("quoted.conf");
+        let detail = MakeConf::new(path).unwrap();
+        assert_eq!(detail.cc.as_deref(), Some("gcc -m64"));
+        assert_eq!(detail.c_extra_libs.libs, vec!["m", "pthread"]);
+        assert_eq!(detail.f_extra_libs.libs, vec!["gfortran", "quadmath"]);
+        // Unterminated quote is stripped
+        assert_eq!(detail.core.as_deref(), Some("HASWELL"));
+        assert_eq!(detail.target.as_deref(), Some("HASWELL # tuned"));
+    }
+
+    #[test]
+    fn detail_spaced() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("spaced.conf");
+        let detail = MakeConf::new(path).unwrap();
+        assert_eq!(detail.os_name, "Linux");
+        assert_eq!(detail.arch.as_deref(), Some("x86_64"));
+        assert_eq!(detail.core.as_deref(), Some("HASWELL"));
+        assert!(detail.interface64);
+        assert!(!detail.no_fortran);
+        assert_eq!(detail.f_extra_libs.libs, vec!["gfortran", "m"]);
+        assert_eq!(detail.c_extra_libs.libs, vec!["m", "pthread"]);
+    }
+
+    #[test]
+    fn detail_crlf() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("crlf.conf");
+        let detail = MakeConf::new(path).unwrap();
+        assert_eq!(detail.os_name, "WINNT");
+        assert_eq!(detail.arch.as_deref(), Some("x86_64"));
+        assert_eq!(detail.cc.as_deref(), Some("gcc"));
+        assert_eq!(detail.core.as_deref(), Some("HASWELL"));
+        assert!(detail.interface64);
+        assert_eq!(detail.c_extra_libs.libs, vec!["m"]);
+        assert_eq!(detail.f_extra_libs.libs, vec!["gfortran", "quadmath"]);
+    }
+
+    #[test]
+    fn detail_target() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Makefile.conf");
+        let detail = MakeConf::new(path).unwrap();
+        assert_eq!(detail.target, None);
+        assert_eq!(detail.core.as_deref(), Some("HASWELL"));
+        assert_eq!(detail.arch.as_deref(), Some("x86_64"));
+
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("target.conf");
+        let detail = MakeConf::new(path).unwrap();
+        assert_eq!(detail.target.as_deref(), Some("SKYLAKEX"));
+        assert_eq!(detail.core, None);
+        assert_eq!(detail.arch.as_deref(), Some("x86_64"));
+    }
+
+    #[test]
+    fn detail_interface64() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("interface64.conf");
+        let detail = MakeConf::new(path).unwrap();
+        assert!(detail.interface64);
+        assert_eq!(detail.symbol_suffix.as_deref(), Some("64_"));
+
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Makefile.conf");
+        let detail = MakeConf::new(path).unwrap();
+        assert!(!detail.interface64);
+        assert_eq!(detail.symbol_suffix, None);
+    }
+
+    #[test]
+    fn getarch_output() {
+        let out = r#"OSNAME=Linux
+ARCH=x86_64
+C_COMPILER=GCC
+BINARY64=1
+CORE=SKYLAKEX
+LIBCORE=skylakex
+NUM_CORES=16
+HAVE_AVX512F=1
+#define OS_LINUX	1
+#define INTEL_AMD
+#define SKYLAKEX
+#define L1_DATA_SIZE 32768
+#define L1_DATA_LINESIZE 64
+#define L2_SIZE 1048576
+#define L3_SIZE 23068672
+#define CORENAME  "SKYLAKEX"
+#define NUM_CORES 16
+"#;
+        let info = parse_getarch(out);
+        assert_eq!(
+            info,
+            GetArchInfo {
+                core: Some("SKYLAKEX".into()),
+                arch: Some("x86_64".into()),
+                num_cores: Some(16),
+                l1_data_size: Some(32768),
+                l2_size: Some(1048576),
+                l3_size: Some(23068672),
+            }
+        );
+        assert!(info.matches_target(&crate::Target::SKYLAKEX));
+        assert!(!info.matches_target(&crate::Target::HASWELL));
+        assert!(!GetArchInfo::default().matches_target(&crate::Target::HASWELL));
+    }
+
+    #[test]
+    fn pkg_config() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("openblas.pc");
+        let pc = PkgConfig::parse(path).unwrap();
+        assert_eq!(pc.variables["libdir"], "/usr/lib");
+        assert_eq!(
+            pc.variables["openblas_config"],
+            "USE_64BITINT= NO_CBLAS= NO_LAPACK="
+        );
+        assert_eq!(pc.libs.libs, vec!["openblas"]);
+        assert_eq!(pc.libs_private.libs, vec!["gfortran", "m", "pthread"]);
+
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("undefined_var.pc");
+        assert!(matches!(
+            PkgConfig::parse(path),
+            Err(Error::PkgConfigUndefinedVariable { name }) if name == "libdir"
+        ));
+    }
+
+    #[test]
+    fn detail_append_and_comment() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("append.conf");
+        let detail = MakeConf::new(path).unwrap();
+        assert_eq!(detail.os_name, "Linux");
+        assert_eq!(detail.c_extra_libs.libs, vec!["c", "m"]);
+        assert_eq!(detail.f_extra_libs.libs, vec!["gfortran", "m", "quadmath"]);
+    }
+
+    #[test]
+    fn detail_non_utf8() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("non_utf8.conf");
+        match MakeConf::new(path) {
+            Err(Error::MakeConfNotUtf8 { line_no }) => assert_eq!(line_no, 3),
+            _ => panic!("Non-UTF8 line must be reported"),
+        }
+    }
+
+    #[test]
+    fn detail_write_roundtrip() {
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let detail = MakeConf::new(root.join("Makefile.conf")).unwrap();
+
+        let dir = root.join("test_build/detail_write_roundtrip");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("Makefile.conf");
+        detail.write(fs::File::create(&path).unwrap()).unwrap();
+        let written = MakeConf::new(&path).unwrap();
+        assert_eq!(written.deps(), vec![path.clone()]);
+        // `path` differs but is not compared
+        assert_eq!(written, detail);
+
+        // `NOFORTRAN=0` is kept
+        let enabled = MakeConf {
+            no_fortran_level: Some(0),
+            ..detail.clone()
+        };
+        let mut buf = Vec::new();
+        enabled.write(&mut buf).unwrap();
+        assert!(String::from_utf8_lossy(&buf).contains("NOFORTRAN=0\n"));
+        let path = dir.join("Makefile_nofortran_0.conf");
+        fs::write(&path, &buf).unwrap();
+        assert_eq!(MakeConf::new(&path).unwrap(), enabled);
+
+        // Write again to check idempotency
+        let mut first = Vec::new();
+        detail.write(&mut first).unwrap();
+        let mut second = Vec::new();
+        written.write(&mut second).unwrap();
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn static_link_order() {
+        let make_conf = MakeConf {
+            f_extra_libs: LinkFlags::parse("-lm -lquadmath -lgfortran -lm").unwrap(),
+            ..MakeConf::default()
+        };
+        assert_eq!(
+            super::static_link_order(&make_conf),
+            vec!["openblas", "gfortran", "quadmath", "m"]
+        );
+    }
+
+    #[test]
+    fn gfortran_linkage() {
+        let with_flags = |flags: &str| MakeConf {
+            f_extra_libs: LinkFlags::parse(flags).unwrap(),
+            ..MakeConf::default()
+        };
+        assert_eq!(with_flags("-lm").gfortran_linkage(), None);
+        assert_eq!(
+            with_flags("-lgfortran -lm").gfortran_linkage(),
+            Some(Linkage::Dynamic)
+        );
+        assert_eq!(
+            with_flags("-l:libgfortran.a -lquadmath").gfortran_linkage(),
+            Some(Linkage::Static)
+        );
+        assert_eq!(
+            with_flags("-l:libgfortran.so.5").gfortran_linkage(),
+            Some(Linkage::Dynamic)
+        );
+
+        let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let dir = root.join("test_build/gfortran_static");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("libgfortran.a"), "").unwrap();
+        let _ = fs::remove_file(dir.join("libgfortran.so"));
+        let flags = format!("-L{} -lgfortran", dir.display());
+        assert_eq!(with_flags(&flags).gfortran_linkage(), Some(Linkage::Static));
+        fs::write(dir.join("libgfortran.so"), "").unwrap();
+        assert_eq!(
+            with_flags(&flags).gfortran_linkage(),
+            Some(Linkage::Dynamic)
+        );
+    }
+
+    #[test]
+    fn deps() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Makefile.conf");
+        assert_eq!(MakeConf::new(&path).unwrap().deps(), vec![path]);
+        assert!(MakeConf::default().deps().is_empty());
+
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("libtiny.a");
+        assert_eq!(LibInspect::new(&path).unwrap().deps(), vec![path]);
+        assert!(LibInspect::from_outputs(b"", b"").deps().is_empty());
+    }
+
+    #[test]
+    fn detail_compilers() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("compilers.conf");
+        let detail = MakeConf::new(path).unwrap();
+        assert_eq!(detail.cc.as_deref(), Some("gcc -m64"));
+        assert_eq!(detail.fc.as_deref(), Some("gfortran"));
+
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("Makefile.conf");
+        let detail = MakeConf::new(path).unwrap();
+        assert_eq!(detail.cc, None);
+        assert_eq!(detail.fc.as_deref(), Some("gfortran"));
     }
 }
@@ -1,4 +1,5 @@
-use std::{io, path::*, process::Command};
+use crate::MakeResult;
+use std::{io, path::*, process::ExitStatus};
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -6,8 +7,36 @@ pub enum Error {
     #[error("Subprocess returns with non-zero status: {status}")]
     NonZeroExitStatus { status: i32 },
 
-    #[error("Subprocess cannot start: {error:?}")]
-    SubprocessCannotStart { error: io::Error },
+    #[error("`make` returns with non-zero status: {}", result.status)]
+    MakeFailed { result: MakeResult },
+
+    #[error("Subprocess cannot start: {error}")]
+    SubprocessCannotStart {
+        #[source]
+        error: io::Error,
+    },
+
+    #[error("Failed to run `{tool}`: {source}")]
+    BinutilTool { tool: String, source: io::Error },
+
+    #[error("`{tool}` returns with {status}: {stderr}")]
+    BinutilFailed {
+        tool: String,
+        status: ExitStatus,
+        stderr: String,
+    },
+
+    #[error("`{tool}` does not exit in time, and is killed")]
+    BinutilTimeout { tool: String },
+
+    #[error("Both static and shared libraries are disabled")]
+    NoLibraryToBuild,
+
+    #[error("All of single, double, complex and complex16 precisions are disabled")]
+    NoPrecisionToBuild,
+
+    #[error("Install prefix is not writable: {}", prefix.display())]
+    PrefixNotWritable { prefix: PathBuf, source: io::Error },
 
     #[error("Fortran compiler not found. It is necessary to build LAPACK.")]
     FortranCompilerNotFound,
@@ -18,30 +47,239 @@ pub enum Error {
     #[error("Makefile.conf does not exist in {}", out_dir.display())]
     MakeConfNotExist { out_dir: PathBuf },
 
+    #[error("Makefile.conf includes non-UTF8 string at line {line_no}")]
+    MakeConfNotUtf8 { line_no: usize },
+
+    #[error("Undefined variable `${{{name}}}` in pkg-config file")]
+    PkgConfigUndefinedVariable { name: String },
+
     #[error("Library file does not exist: {}", path.display())]
     LibraryNotExist { path: PathBuf },
 
-    #[error("Other IO errors: {0:?}")]
+    #[error("More than one member of {} matches: {}", archive.display(), matches.join(", "))]
+    AmbiguousArchiveMember {
+        archive: PathBuf,
+        matches: Vec<String>,
+    },
+
+    #[error("Linked library is not found: {name}")]
+    DependencyNotFound { name: String },
+
+    #[error("Unsupported target: {target}")]
+    UnsupportedTarget { target: String },
+
+    #[error(
+        "SYMBOLSUFFIX of Makefile.conf is {expected_suffix:?} but symbol suffix of library is {symbol_suffix:?}"
+    )]
+    InterfaceMismatch {
+        expected_suffix: Option<String>,
+        symbol_suffix: Option<String>,
+    },
+
+    #[error("Other IO errors: {0}")]
     IOError(#[from] io::Error),
 }
 
-pub(crate) trait CheckCall {
-    fn check_call(&mut self) -> Result<(), Error>;
+impl Error {
+    /// Suggestion to fix the error, shown to users in build scripts
+    pub fn user_hint(&self) -> Option<&'static str> {
+        let hint = match self {
+            Error::NonZeroExitStatus { .. } => "A subprocess failed; see its output above",
+            Error::MakeFailed { .. } => {
+                "`make` of OpenBLAS failed; see err.log in the output directory"
+            }
+            Error::SubprocessCannotStart { .. } => "Check `make` is installed and found in PATH",
+            Error::BinutilTool { .. } => {
+                "Install GNU binutils, or specify the tools by NM and OBJDUMP environment variables"
+            }
+            Error::BinutilFailed { .. } => {
+                "The library may be broken, or the tools do not support its format e.g. of cross compiling"
+            }
+            Error::BinutilTimeout { .. } => {
+                "Check the filesystem of the library is responsive, or the library is not corrupted"
+            }
+            Error::PkgConfigUndefinedVariable { .. } => {
+                "The pkg-config file is broken; reinstall OpenBLAS"
+            }
+            Error::NoLibraryToBuild => "Enable either static or shared library",
+            Error::NoPrecisionToBuild => "Enable at least one of precisions, e.g. `build_double`",
+            Error::PrefixNotWritable { .. } => {
+                "Check the permission of the install prefix, or use a directory under your home"
+            }
+            Error::FortranCompilerNotFound => {
+                "Install a Fortran compiler e.g. gfortran, or disable LAPACK"
+            }
+            Error::CannotCanonicalizePath { .. } => {
+                "Check the permission of the directories in linker flags"
+            }
+            Error::MakeConfNotExist { .. } => {
+                "The OpenBLAS build likely failed; remove the output directory and rebuild"
+            }
+            Error::MakeConfNotUtf8 { .. } => {
+                "Makefile.conf is broken; remove the output directory and rebuild"
+            }
+            Error::LibraryNotExist { .. } => {
+                "The OpenBLAS build likely failed; remove the output directory and rebuild"
+            }
+            Error::AmbiguousArchiveMember { .. } => {
+                "Use a more specific pattern, e.g. `*/lib/libopenblas.a`"
+            }
+            Error::DependencyNotFound { .. } => {
+                "Install the library, or add its directory to LD_LIBRARY_PATH"
+            }
+            Error::UnsupportedTarget { .. } => {
+                "Use a target listed in TargetList.txt of OpenBLAS, e.g. HASWELL"
+            }
+            Error::InterfaceMismatch { .. } => {
+                "Makefile.conf and the library are from different builds; remove the output directory and rebuild"
+            }
+            Error::IOError(_) => {
+                "Check the free disk space and the permission of the output directory"
+            }
+        };
+        Some(hint)
+    }
 }
 
-impl CheckCall for Command {
-    fn check_call(&mut self) -> Result<(), Error> {
-        match self.status() {
-            Ok(status) => {
-                if !status.success() {
-                    Err(Error::NonZeroExitStatus {
-                        status: status.code().unwrap_or(-1),
-                    })
-                } else {
-                    Ok(())
-                }
-            }
-            Err(error) => Err(Error::SubprocessCannotStart { error }),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::error::Error as _;
+
+    /// Every variant, with a part of its message and whether it has a source error
+    fn examples() -> Vec<(Error, &'static str, bool)> {
+        vec![
+            (Error::NonZeroExitStatus { status: 2 }, "status: 2", false),
+            (
+                Error::MakeFailed {
+                    result: MakeResult {
+                        status: std::os::unix::process::ExitStatusExt::from_raw(2 << 8),
+                        stdout: Vec::new(),
+                        stderr: b"gfortran: command not found".to_vec(),
+                    },
+                },
+                "exit status: 2",
+                false,
+            ),
+            (
+                Error::SubprocessCannotStart {
+                    error: io::Error::new(io::ErrorKind::NotFound, "make not found"),
+                },
+                "make not found",
+                true,
+            ),
+            (
+                Error::BinutilTool {
+                    tool: "nm".into(),
+                    source: io::ErrorKind::NotFound.into(),
+                },
+                "`nm`",
+                true,
+            ),
+            (
+                Error::BinutilFailed {
+                    tool: "objdump".into(),
+                    status: std::os::unix::process::ExitStatusExt::from_raw(1 << 8),
+                    stderr: "objdump: file format not recognized".into(),
+                },
+                "`objdump` returns with exit status: 1: objdump: file format",
+                false,
+            ),
+            (
+                Error::BinutilTimeout { tool: "nm".into() },
+                "`nm` does not exit",
+                false,
+            ),
+            (
+                Error::PkgConfigUndefinedVariable {
+                    name: "prefix".into(),
+                },
+                "${prefix}",
+                false,
+            ),
+            (Error::NoLibraryToBuild, "static and shared", false),
+            (Error::NoPrecisionToBuild, "precisions are disabled", false),
+            (
+                Error::PrefixNotWritable {
+                    prefix: "/usr".into(),
+                    source: io::ErrorKind::PermissionDenied.into(),
+                },
+                "/usr",
+                true,
+            ),
+            (Error::FortranCompilerNotFound, "Fortran", false),
+            (
+                Error::CannotCanonicalizePath {
+                    path: "/usr/lib".into(),
+                },
+                "/usr/lib",
+                false,
+            ),
+            (
+                Error::MakeConfNotExist {
+                    out_dir: "/tmp/out".into(),
+                },
+                "/tmp/out",
+                false,
+            ),
+            (Error::MakeConfNotUtf8 { line_no: 3 }, "line 3", false),
+            (
+                Error::LibraryNotExist {
+                    path: "libopenblas.so".into(),
+                },
+                "libopenblas.so",
+                false,
+            ),
+            (
+                Error::AmbiguousArchiveMember {
+                    archive: "openblas.tar".into(),
+                    matches: vec!["lib/libopenblas.a".into(), "lib/libopenblas.so".into()],
+                },
+                "lib/libopenblas.a, lib/libopenblas.so",
+                false,
+            ),
+            (
+                Error::DependencyNotFound {
+                    name: "libgfortran.so.5".into(),
+                },
+                "libgfortran.so.5",
+                false,
+            ),
+            (
+                Error::UnsupportedTarget {
+                    target: "PENTIUM".into(),
+                },
+                "PENTIUM",
+                false,
+            ),
+            (
+                Error::InterfaceMismatch {
+                    expected_suffix: Some("64_".into()),
+                    symbol_suffix: None,
+                },
+                "SYMBOLSUFFIX",
+                false,
+            ),
+            (
+                Error::IOError(io::Error::other("disk full")),
+                "disk full",
+                true,
+            ),
+        ]
+    }
+
+    #[test]
+    fn user_hint() {
+        for (error, _, _) in examples() {
+            assert!(error.user_hint().is_some(), "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn display() {
+        for (error, message, has_source) in examples() {
+            assert!(error.to_string().contains(message), "{}", error);
+            assert_eq!(error.source().is_some(), has_source, "{}", error);
         }
     }
 }
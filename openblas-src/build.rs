@@ -92,7 +92,13 @@ fn main() {
 /// Build OpenBLAS using openblas-build crate
 #[cfg(target_os = "linux")]
 fn build() {
-    let mut cfg = openblas_build::Configure::default();
+    for key in openblas_build::CONFIGURE_ENV_VARS {
+        println!("cargo:rerun-if-env-changed={}", key);
+    }
+    let mut cfg = match openblas_build::Configure::from_env() {
+        Ok(cfg) => cfg,
+        Err(err) => panic_with_hint(err),
+    };
     if !feature_enabled("cblas") {
         cfg.no_cblas = true;
     }
@@ -104,6 +110,15 @@ fn build() {
     } else {
         cfg.no_static = true;
     }
+    // OpenBLAS detects the CPU of build machine, which is not the target machine in cross compiling
+    let (target, host) = (env::var("TARGET").unwrap(), env::var("HOST").unwrap());
+    // Keep the target given explicitly by `OPENBLAS_TARGET`
+    if target != host && cfg.target.is_none() {
+        cfg.target = openblas_build::Target::from_rust_triple(&target);
+    }
+    if let Some(warning) = cfg.rust_triple_warning(&target) {
+        println!("cargo:warning={}", warning);
+    }
 
     let output = if feature_enabled("cache") {
         use std::{collections::hash_map::DefaultHasher, hash::*};
@@ -145,21 +160,61 @@ fn build() {
     }
 
     let source = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("source");
-    let deliv = cfg.build(&source, &output).unwrap();
+    let deliv = match cfg.build(&source, &output) {
+        Ok(deliv) => deliv,
+        Err(err) => panic_with_hint(err),
+    };
+
+    let libs = deliv.static_lib.iter().chain(deliv.shared_lib.iter());
+    for lib in libs.clone() {
+        if lib.is_probably_unstripped() {
+            println!(
+                "cargo:warning={} is {} MB, probably with debug info. Consider stripping it.",
+                lib.path().display(),
+                lib.size_bytes / 1024 / 1024
+            );
+        }
+        if lib.has_absolute_runpath() {
+            println!(
+                "cargo:warning={} has absolute run-time paths {:?}, and is not relocatable",
+                lib.path().display(),
+                lib.runpaths()
+            );
+        }
+        // Only warn since the symbol suffix cannot be detected for every build
+        if let Err(err) = openblas_build::validate_interface64(&deliv.make_conf, lib) {
+            println!("cargo:warning={}", err);
+        }
+    }
 
     println!("cargo:rustc-link-search={}", output.display());
-    for search_path in &deliv.make_conf.c_extra_libs.search_paths {
-        println!("cargo:rustc-link-search={}", search_path.display());
+    for path in deliv
+        .make_conf
+        .deps()
+        .into_iter()
+        .chain(libs.flat_map(|lib| lib.deps()))
+    {
+        println!("cargo:rerun-if-changed={}", path.display());
     }
-    for lib in &deliv.make_conf.c_extra_libs.libs {
-        println!("cargo:rustc-link-lib={}", lib);
+    let mut extra_libs = deliv
+        .make_conf
+        .c_extra_libs
+        .merge(&deliv.make_conf.f_extra_libs);
+    for lib in deliv.static_lib.iter().chain(deliv.shared_lib.iter()) {
+        extra_libs = extra_libs.merge(&lib.needed_as_link_flags(&deliv.make_conf));
     }
-    for search_path in &deliv.make_conf.f_extra_libs.search_paths {
-        println!("cargo:rustc-link-search={}", search_path.display());
+    for directive in extra_libs.to_cargo_directives() {
+        println!("{}", directive);
     }
-    for lib in &deliv.make_conf.f_extra_libs.libs {
-        println!("cargo:rustc-link-lib={}", lib);
+}
+
+/// Abort the build script with the hint for users if available
+#[cfg(target_os = "linux")]
+fn panic_with_hint(err: openblas_build::error::Error) -> ! {
+    if let Some(hint) = err.user_hint() {
+        eprintln!("hint: {}", hint);
     }
+    panic!("{}", err);
 }
 
 /// openblas-src 0.9.0 compatible `make` runner